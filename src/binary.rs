@@ -0,0 +1,135 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+/// What to do with matched files that look binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum BinaryPolicy {
+    /// Leave them out (reported on stderr and counted in `--stats`).
+    #[default]
+    Skip,
+    /// Print the usual header with a one-line note instead of the contents.
+    Placeholder,
+    /// Print the contents base64-encoded (JSON output only).
+    Base64,
+}
+
+/// The content printed for a binary file of format `kind` under `policy`.
+pub fn substitute(policy: BinaryPolicy, kind: &str, bytes: &[u8]) -> String {
+    match policy {
+        BinaryPolicy::Base64 => STANDARD.encode(bytes),
+        BinaryPolicy::Skip | BinaryPolicy::Placeholder => {
+            format!("[binary file: {kind}, {} bytes]\n", bytes.len())
+        }
+    }
+}
+
+/// How many leading bytes of a file are inspected.
+pub const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Leading bytes of common binary formats.
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "png"),
+    (b"\xff\xd8\xff", "jpeg"),
+    (b"GIF87a", "gif"),
+    (b"GIF89a", "gif"),
+    (b"%PDF-", "pdf"),
+    (b"PK\x03\x04", "zip"),
+    (b"PK\x05\x06", "zip"),
+    (b"\x1f\x8b", "gzip"),
+    (b"\xfd7zXZ\x00", "xz"),
+    (b"7z\xbc\xaf\x27\x1c", "7z"),
+    (b"\x28\xb5\x2f\xfd", "zstd"),
+    (b"Rar!\x1a\x07", "rar"),
+    (b"!<arch>\n", "ar"),
+    (b"\x7fELF", "elf"),
+    (b"\xca\xfe\xba\xbe", "mach-o"),
+    (b"\xcf\xfa\xed\xfe", "mach-o"),
+    (b"\xce\xfa\xed\xfe", "mach-o"),
+    (b"\x00asm", "wasm"),
+    (b"SQLite format 3\x00", "sqlite"),
+    (b"OggS", "ogg"),
+    (b"fLaC", "flac"),
+    (b"ID3", "mp3"),
+    (b"wOFF", "woff"),
+    (b"wOF2", "woff2"),
+];
+
+/// Above this share of invalid UTF-8 bytes, a file is not text.
+const MAX_INVALID_RATIO: f64 = 0.1;
+
+/// Whether `bytes` (the start of a file) look binary, and if so a short
+/// name for the format: a known magic number (`"gzip"`, `"png"`, ...), or
+/// `"binary"` for NUL bytes or mostly invalid UTF-8.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, kind)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return Some(kind);
+    }
+
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    if sample.contains(&0) || invalid_utf8_ratio(sample) > MAX_INVALID_RATIO {
+        return Some("binary");
+    }
+
+    None
+}
+
+/// Share of bytes in `sample` that are not part of valid UTF-8. A sequence
+/// cut off at the end of the sample is not counted.
+fn invalid_utf8_ratio(sample: &[u8]) -> f64 {
+    if sample.is_empty() {
+        return 0.0;
+    }
+
+    let mut invalid = 0;
+    let mut rest = sample;
+    while let Err(err) = std::str::from_utf8(rest) {
+        let Some(len) = err.error_len() else {
+            break;
+        };
+        invalid += len;
+        rest = &rest[err.valid_up_to() + len..];
+    }
+
+    invalid as f64 / sample.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_magic_numbers_and_nul_bytes() {
+        assert_eq!(sniff(b"\x1f\x8b\x08\x00rest"), Some("gzip"));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n...."), Some("png"));
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some("pdf"));
+        assert_eq!(sniff(b"text\x00more"), Some("binary"));
+        assert_eq!(sniff(&[0x80; 100]), Some("binary"));
+    }
+
+    #[test]
+    fn substitutes_a_note_or_base64() {
+        let bytes = b"\x1f\x8b\x08\x00";
+        assert_eq!(
+            substitute(BinaryPolicy::Placeholder, "gzip", bytes),
+            "[binary file: gzip, 4 bytes]\n"
+        );
+        assert_eq!(substitute(BinaryPolicy::Base64, "gzip", bytes), "H4sIAA==");
+    }
+
+    #[test]
+    fn text_is_not_binary() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"fn main() {}\n"), None);
+        assert_eq!(sniff("na\u{ef}ve caf\u{e9} \u{1f980}\n".as_bytes()), None);
+
+        // A few Latin-1 bytes in otherwise ASCII text.
+        let mut latin1 = b"caf\xe9 au lait, ".repeat(3);
+        latin1.extend_from_slice(&b"plain ascii text ".repeat(10));
+        assert_eq!(sniff(&latin1), None);
+
+        // A multi-byte character cut off by the sample limit.
+        let mut cut = "a".repeat(SNIFF_BYTES - 1).into_bytes();
+        cut.extend_from_slice("\u{e9}".as_bytes());
+        assert_eq!(sniff(&cut), None);
+    }
+}
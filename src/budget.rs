@@ -0,0 +1,242 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, bail};
+use globset::GlobSet;
+
+use crate::Document;
+use crate::tokens::{Tokenizer, approx_tokens, count_tokens};
+
+/// A cap on the combined size of all emitted files.
+///
+/// Parsed from `--budget`: a plain number (optionally with a `k` / `m`
+/// multiplier) is a token count; a `B`, `KB` or `MB` suffix makes it bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    Tokens(u64),
+    Bytes(u64),
+}
+
+impl Budget {
+    pub(crate) fn limit(self) -> u64 {
+        match self {
+            Budget::Tokens(n) | Budget::Bytes(n) => n,
+        }
+    }
+
+    pub(crate) fn unit(self) -> &'static str {
+        match self {
+            Budget::Tokens(_) => "tokens",
+            Budget::Bytes(_) => "bytes",
+        }
+    }
+
+    /// Size of arbitrary rendered text in this budget's unit.
+    pub(crate) fn measure(self, text: &str, tokenizer: Tokenizer) -> u64 {
+        match self {
+            Budget::Tokens(_) => count_tokens(text, tokenizer) as u64,
+            Budget::Bytes(_) => text.len() as u64,
+        }
+    }
+
+    /// Cost of a document in this budget's unit.
+    fn cost(self, doc: &Document) -> u64 {
+        match self {
+            Budget::Tokens(_) => doc.tokens.unwrap_or_else(|| approx_tokens(&doc.text)) as u64,
+            Budget::Bytes(_) => doc.text.len() as u64,
+        }
+    }
+}
+
+impl FromStr for Budget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (digits, suffix) = s.split_at(split);
+
+        let n: u64 = digits
+            .parse()
+            .with_context(|| format!("Invalid budget {s:?}: expected a number"))?;
+
+        let (unit, scale): (fn(u64) -> Budget, u64) = match suffix.to_ascii_lowercase().as_str() {
+            "" => (Budget::Tokens, 1),
+            "k" => (Budget::Tokens, 1_000),
+            "m" => (Budget::Tokens, 1_000_000),
+            "b" => (Budget::Bytes, 1),
+            "kb" => (Budget::Bytes, 1_024),
+            "mb" => (Budget::Bytes, 1_024 * 1_024),
+            _ => bail!("Invalid budget {s:?}: unknown suffix {suffix:?} (use k, m, B, KB or MB)"),
+        };
+
+        match n.checked_mul(scale) {
+            Some(n) => Ok(unit(n)),
+            None => bail!("Invalid budget {s:?}: budget too large"),
+        }
+    }
+}
+
+/// Order in which files are considered for inclusion under a budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Priority {
+    /// Traversal order.
+    #[default]
+    Walk,
+    /// Smallest files first (fits the most files).
+    Smallest,
+    /// Most recently modified files first.
+    Recent,
+    /// Files closest to the root first.
+    Shallow,
+}
+
+/// Split `docs` into those that fit within `budget` and those that don't.
+///
+/// Files are considered in priority order: first by the index of the first
+/// `prefer` glob they match (non-matching files last), then by `priority`. A
+/// file that would push the total over the budget is dropped and the next one
+/// is tried, so smaller files later in the order can still fit. Kept files are
+/// returned in their original order.
+pub(crate) fn select(
+    docs: Vec<Document>,
+    budget: Budget,
+    priority: Priority,
+    prefer: Option<&GlobSet>,
+) -> (Vec<Document>, Vec<Document>) {
+    let mut order: Vec<usize> = (0..docs.len()).collect();
+
+    let preferred = |doc: &Document| -> usize {
+        prefer
+            .and_then(|gs| gs.matches(&doc.display_path).into_iter().min())
+            .unwrap_or(usize::MAX)
+    };
+
+    match priority {
+        Priority::Walk => order.sort_by_key(|&i| preferred(&docs[i])),
+        Priority::Smallest => order.sort_by_key(|&i| (preferred(&docs[i]), budget.cost(&docs[i]))),
+        Priority::Recent => {
+            let modified: Vec<Option<SystemTime>> = docs
+                .iter()
+                .map(|d| fs::metadata(&d.path).and_then(|m| m.modified()).ok())
+                .collect();
+            order.sort_by_key(|&i| (preferred(&docs[i]), Reverse(modified[i])));
+        }
+        Priority::Shallow => order.sort_by_key(|&i| {
+            let depth = docs[i].display_path.matches('/').count();
+            (preferred(&docs[i]), depth)
+        }),
+    }
+
+    let mut keep = vec![false; docs.len()];
+    let mut used: u64 = 0;
+    for i in order {
+        let cost = budget.cost(&docs[i]);
+        if used + cost <= budget.limit() {
+            used += cost;
+            keep[i] = true;
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    for (doc, keep) in docs.into_iter().zip(keep) {
+        if keep {
+            kept.push(doc);
+        } else {
+            dropped.push(doc);
+        }
+    }
+
+    (kept, dropped)
+}
+
+/// List files dropped by the budget on stderr.
+pub(crate) fn report_dropped(dropped: &[Document], budget: Budget) {
+    if dropped.is_empty() {
+        return;
+    }
+
+    let total: u64 = dropped.iter().map(|d| budget.cost(d)).sum();
+    eprintln!(
+        "Budget of {} {} exceeded: dropped {} files ({} {}):",
+        budget.limit(),
+        budget.unit(),
+        dropped.len(),
+        total,
+        budget.unit()
+    );
+    for doc in dropped {
+        eprintln!(
+            "  {} ({} {})",
+            doc.display_path,
+            budget.cost(doc),
+            budget.unit()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn doc(path: &str, text: &str) -> Document {
+        Document {
+            path: PathBuf::from(path),
+            display_path: path.to_string(),
+            root: ".".to_string(),
+            language: None,
+            text: text.to_string(),
+            tokens: None,
+            line_numbers: None,
+            chunk: None,
+            binary: None,
+            meta: None,
+        }
+    }
+
+    fn paths(docs: &[Document]) -> Vec<&str> {
+        docs.iter().map(|d| d.display_path.as_str()).collect()
+    }
+
+    #[test]
+    fn budget_parses_tokens_and_bytes() {
+        assert_eq!("8000".parse::<Budget>().unwrap(), Budget::Tokens(8000));
+        assert_eq!("8k".parse::<Budget>().unwrap(), Budget::Tokens(8000));
+        assert_eq!("500B".parse::<Budget>().unwrap(), Budget::Bytes(500));
+        assert_eq!("2KB".parse::<Budget>().unwrap(), Budget::Bytes(2048));
+        assert!("12xyz".parse::<Budget>().is_err());
+        assert!("KB".parse::<Budget>().is_err());
+        assert!("18446744073709551m".parse::<Budget>().is_err());
+        assert!("18014398509481984KB".parse::<Budget>().is_err());
+    }
+
+    #[test]
+    fn select_skips_files_that_do_not_fit_and_keeps_walk_order() {
+        let docs = vec![doc("a", "aaaa"), doc("b", "bbbbbbbb"), doc("c", "cc")];
+        let (kept, dropped) = select(docs, Budget::Bytes(6), Priority::Walk, None);
+        assert_eq!(paths(&kept), ["a", "c"]);
+        assert_eq!(paths(&dropped), ["b"]);
+    }
+
+    #[test]
+    fn select_smallest_first_fits_most_files() {
+        let docs = vec![doc("a", "aaaaa"), doc("b", "bb"), doc("c", "ccc")];
+        let (kept, _) = select(docs, Budget::Bytes(5), Priority::Smallest, None);
+        assert_eq!(paths(&kept), ["b", "c"]);
+    }
+
+    #[test]
+    fn select_considers_preferred_globs_first() {
+        let mut builder = globset::GlobSetBuilder::new();
+        builder.add(globset::Glob::new("src/**").unwrap());
+        let prefer = builder.build().unwrap();
+
+        let docs = vec![doc("docs/a.md", "aaaa"), doc("src/b.rs", "bbbb")];
+        let (kept, _) = select(docs, Budget::Bytes(4), Priority::Walk, Some(&prefer));
+        assert_eq!(paths(&kept), ["src/b.rs"]);
+    }
+}
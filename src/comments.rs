@@ -1,26 +1,199 @@
+use anyhow::{Context, Result};
+use regex::RegexSet;
+
+use crate::lang::Registry;
+
+/// How much `--strip-comments` removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum StripLevel {
+    /// Full-line comments (by leader) and blank lines only.
+    #[default]
+    Lines,
+    /// Also block comments (`/* */`, `<!-- -->`, `--[[ ]]`, ...), using a
+    /// lexer that skips string literals.
+    Blocks,
+    /// Also trailing comments after code (`x = 1  # note`), for languages
+    /// where they are unambiguous.
+    Aggressive,
+}
+
+/// Comments kept by default even when stripping, matched against the comment
+/// text starting at its marker.
+pub const DEFAULT_KEEP: &[&str] = &[
+    // Rust `///` / `//!` docs, TypeScript `/// <reference />` directives.
+    r"^//[/!]",
+    // Rust `/*!` inner docs, `/*!` license blocks preserved by minifiers.
+    r"^/\*!",
+    // Shebangs.
+    r"^#!",
+    // Python source encoding (PEP 263) and editor modelines.
+    r"^#.*coding[:=]",
+    r"^(#|//)\s*vim?:",
+    // Justifications for `unsafe` blocks.
+    r"^//\s*SAFETY:",
+    // Type-checker and linter pragmas.
+    r"^#\s*(type:|noqa\b|pyright:|mypy:|pylint:|ruff:)",
+    r"^//\s*@ts-(ignore|expect-error|nocheck)",
+    r"\b(eslint|jshint)-(disable|enable)",
+    r"\bNOLINT",
+    // Formatter markers.
+    r"\b(fmt|yapf|isort):\s*(off|on|skip)",
+    r"\bclang-format (off|on)",
+    r"\bprettier-ignore",
+    r"\brustfmt::skip",
+    // Haskell `{-# LANGUAGE ... #-}` pragmas.
+    r"^\{-#",
+    r"@formatter:(off|on)",
+];
+
+/// Comments that survive `--strip-comments`.
+#[derive(Debug, Clone)]
+pub struct KeepList {
+    patterns: RegexSet,
+}
+
+impl KeepList {
+    /// Build a keep-list from user `patterns`, plus [`DEFAULT_KEEP`] when
+    /// `defaults` is set.
+    pub fn new(patterns: &[String], defaults: bool) -> Result<Self> {
+        let builtin = DEFAULT_KEEP.iter().copied().filter(|_| defaults);
+        let user = patterns.iter().map(String::as_str);
+
+        for pat in patterns {
+            regex::Regex::new(pat)
+                .with_context(|| format!("Invalid --keep-comment pattern: {pat}"))?;
+        }
+
+        let patterns = RegexSet::new(builtin.chain(user)).context("Failed to build keep-list")?;
+        Ok(KeepList { patterns })
+    }
+
+    /// Whether `comment` (starting at its marker) should be kept.
+    pub fn keeps(&self, comment: &str) -> bool {
+        self.patterns.is_match(comment)
+    }
+}
+
+impl Default for KeepList {
+    fn default() -> Self {
+        KeepList {
+            patterns: RegexSet::new(DEFAULT_KEEP).expect("built-in keep patterns are valid"),
+        }
+    }
+}
+
+/// Comment and string syntax of a language, used by the lexer. Deserialized
+/// from the `[language.comments]` table of the language registry.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommentSyntax {
+    /// Line comment leaders, e.g. `//` or `#`.
+    pub line: Vec<String>,
+    /// Block comment `(open, close)` pairs, e.g. `("/*", "*/")`.
+    pub block: Vec<(String, String)>,
+    /// Whether block comments nest (Rust, Swift, Haskell, ...).
+    pub nested: bool,
+    /// Only treat a line leader as a comment at the start of a line or after
+    /// whitespace (shell `$#`, YAML `a#b`).
+    pub line_needs_space: bool,
+    /// Whether a line comment may follow code on the same line. When unset,
+    /// trailing leaders are left alone even at the aggressive level (INI,
+    /// Dockerfile, Vim script).
+    pub trailing: bool,
+    /// A backslash outside strings escapes the next character, so it cannot
+    /// open a string or a comment (shell `don\'t`, `\#`).
+    pub escape_outside: bool,
+    /// String literal forms, tried in order.
+    pub strings: Vec<StringSyntax>,
+}
+
+/// A string literal form the lexer must skip over.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum StringSyntax {
+    /// `open ... close`, with an optional escape character. Unless
+    /// `multiline`, an unterminated string ends at the end of the line. With
+    /// `doubled`, a doubled `close` is an escaped one (C# `@"say ""hi"""`).
+    Quoted {
+        open: String,
+        close: String,
+        #[serde(default)]
+        escape: Option<char>,
+        #[serde(default)]
+        multiline: bool,
+        #[serde(default)]
+        doubled: bool,
+    },
+    /// Rust raw strings: `r"..."`, `r#"..."#`, `br##"..."##`.
+    RustRaw,
+    /// Rust char literals (`'a'`, `'\n'`), told apart from lifetimes.
+    RustChar,
+    /// C++ raw strings: `R"delim(...)delim"`.
+    CppRaw,
+    /// Lua long strings: `[[...]]`, `[==[...]==]`.
+    LuaLong,
+    /// Python f-strings (`f"{d['#']:>{w}}"`), including nested quotes in
+    /// replacement fields.
+    #[serde(rename = "py-fstring")]
+    PyFString,
+    /// JavaScript regex literals (`/https?:\/\//g`), where an expression
+    /// is expected.
+    JsRegex,
+    /// Here-documents: `<<EOF`, `<<-EOF`, `<<~EOF`, `<<'EOF'`, from the
+    /// marker through the line holding only the delimiter.
+    Heredoc,
+}
+
+impl Default for CommentSyntax {
+    fn default() -> Self {
+        CommentSyntax {
+            line: Vec::new(),
+            block: Vec::new(),
+            nested: false,
+            line_needs_space: false,
+            trailing: true,
+            escape_outside: false,
+            strings: Vec::new(),
+        }
+    }
+}
+
 /// Conservatively strip full-line comments and blank lines.
 ///
 /// - For known extensions, drop lines where the first non-whitespace chars
-///   match the comment leader (#, //, --, etc.).
+///   match the comment leader (#, //, --, etc.), unless the default
+///   [`KeepList`] keeps them.
 /// - For all files, drop completely blank lines.
 /// - Does NOT touch inline/trailing comments or block comments.
 pub fn strip_comments_for_ext(src: &str, ext: &str) -> String {
-    let ext = ext.to_ascii_lowercase();
-
-    let leaders: &[&str] = match ext.as_str() {
-        // Hash comments
-        "py" | "sh" | "bash" | "zsh" | "rb" | "yaml" | "yml" | "toml" => &["#"],
-        // C-like // comments
-        "rs" | "c" | "h" | "cpp" | "hpp" | "cc" | "js" | "ts" | "java" | "go" | "cs" | "swift"
-        | "kt" => &["//"],
-        // SQL-ish
-        "sql" => &["--"],
-        _ => &[],
-    };
-
     let mut out = String::with_capacity(src.len());
 
-    for line in src.lines() {
+    let syntax = Registry::builtin()
+        .for_ext(ext)
+        .and_then(|lang| lang.comments.as_ref());
+
+    for (_, line) in strip_comment_lines(src, syntax, &KeepList::default()) {
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Same as [`strip_comments_for_ext`], but returns the kept lines paired with
+/// their 1-based line numbers in `src`.
+pub fn strip_comment_lines<'a>(
+    src: &'a str,
+    syntax: Option<&CommentSyntax>,
+    keep: &KeepList,
+) -> Vec<(usize, &'a str)> {
+    let leaders = syntax
+        .map(|syntax| syntax.line.as_slice())
+        .unwrap_or_default();
+
+    let mut out = Vec::new();
+
+    for (idx, line) in src.lines().enumerate() {
         let trimmed = line.trim_start();
 
         // Drop purely blank lines.
@@ -28,21 +201,598 @@ pub fn strip_comments_for_ext(src: &str, ext: &str) -> String {
             continue;
         }
 
-        if !leaders.is_empty() && leaders.iter().any(|leader| trimmed.starts_with(leader)) {
+        if leaders.iter().any(|leader| trimmed.starts_with(leader)) && !keep.keeps(trimmed) {
             continue;
         }
 
-        out.push_str(line);
-        out.push('\n');
+        out.push((idx + 1, line));
+    }
+
+    out
+}
+
+/// Strip comments at the given level, returning the kept lines paired with
+/// their 1-based line numbers in `src`. Comments matched by `keep` survive;
+/// blank lines are always dropped.
+pub fn strip_comments(
+    src: &str,
+    syntax: Option<&CommentSyntax>,
+    level: StripLevel,
+    keep: &KeepList,
+) -> Vec<(usize, String)> {
+    let lexed = match (level, syntax) {
+        (StripLevel::Lines, _) | (_, None) => {
+            return strip_comment_lines(src, syntax, keep)
+                .into_iter()
+                .map(|(n, line)| (n, line.to_string()))
+                .collect();
+        }
+        (StripLevel::Blocks, Some(syntax)) => strip_with_lexer(src, syntax, false, keep),
+        (StripLevel::Aggressive, Some(syntax)) => strip_with_lexer(src, syntax, true, keep),
+    };
+
+    // The lexer keeps every newline, so line `i` of its output is line `i`
+    // of the source.
+    lexed
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| (idx + 1, line.to_string()))
+        .collect()
+}
+
+/// Remove block comments and full-line comments (and, with `trailing`, line
+/// comments after code), skipping over string literals and comments matched
+/// by `keep`. Newlines inside removed comments are kept, so the output has
+/// exactly as many lines as `src`; lines that only held a comment are left
+/// blank.
+fn strip_with_lexer(src: &str, syntax: &CommentSyntax, trailing: bool, keep: &KeepList) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut i = 0;
+    // Whether the current line has code (anything but whitespace) so far.
+    let mut line_has_code = false;
+
+    while i < src.len() {
+        let rest = &src[i..];
+        let prev = src[..i].chars().next_back();
+
+        if let Some(len) = escaped_len(rest, syntax) {
+            out.push_str(&rest[..len]);
+            line_has_code = true;
+            i += len;
+            continue;
+        }
+
+        if let Some(len) = match_string(&src[..i], rest, &syntax.strings) {
+            out.push_str(&rest[..len]);
+            line_has_code = true;
+            i += len;
+            continue;
+        }
+
+        if let Some((open, close)) = syntax.block.iter().find(|(open, _)| rest.starts_with(open)) {
+            let len = block_comment_len(rest, open, close, syntax.nested);
+            if keep.keeps(&rest[..len]) {
+                out.push_str(&rest[..len]);
+                line_has_code = true;
+                i += len;
+                continue;
+            }
+
+            let newlines = rest[..len].matches('\n').count();
+
+            if newlines > 0 {
+                trim_trailing_blanks(&mut out);
+                out.extend(std::iter::repeat_n('\n', newlines));
+                line_has_code = false;
+            }
+
+            let after = &rest[len..];
+            let next = after.chars().next();
+            i += len;
+
+            if next.is_none_or(|c| c == '\n' || c == '\r') {
+                trim_trailing_blanks(&mut out);
+            } else if !line_has_code {
+                // `    /* x */ foo` keeps its indentation but not the gap.
+                i += after.len() - after.trim_start_matches([' ', '\t']).len();
+            } else if out.chars().next_back().is_some_and(|c| !c.is_whitespace())
+                && next.is_some_and(|c| !c.is_whitespace())
+            {
+                // Keep tokens on either side of `a/* x */b` apart.
+                out.push(' ');
+            }
+
+            continue;
+        }
+
+        let at_boundary = prev.is_none_or(char::is_whitespace);
+        if (at_boundary || !syntax.line_needs_space)
+            && syntax.line.iter().any(|leader| rest.starts_with(leader))
+        {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            let kept = keep.keeps(&rest[..len])
+                || line_has_code
+                    && (!trailing
+                        || !syntax.trailing
+                        || rest.starts_with("//") && is_url_scheme(&src[..i]));
+            if kept {
+                out.push_str(&rest[..len]);
+            } else {
+                trim_trailing_blanks(&mut out);
+            }
+            i += len;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        out.push(c);
+        if c == '\n' {
+            line_has_code = false;
+        } else if !c.is_whitespace() {
+            line_has_code = true;
+        }
+        i += c.len_utf8();
     }
 
     out
 }
 
+/// For each line of `src`, whether it starts inside a string literal (a
+/// multi-line or raw string), where whitespace is part of the value.
+pub(crate) fn lines_in_strings(src: &str, syntax: &CommentSyntax) -> Vec<bool> {
+    let mut inside = vec![false];
+    let mut i = 0;
+
+    while i < src.len() {
+        let rest = &src[i..];
+
+        if let Some(len) = escaped_len(rest, syntax) {
+            i += len;
+            continue;
+        }
+
+        if let Some(len) = match_string(&src[..i], rest, &syntax.strings) {
+            inside.extend(std::iter::repeat_n(true, rest[..len].matches('\n').count()));
+            i += len;
+            continue;
+        }
+
+        if let Some((open, close)) = syntax.block.iter().find(|(open, _)| rest.starts_with(open)) {
+            let len = block_comment_len(rest, open, close, syntax.nested);
+            inside.extend(std::iter::repeat_n(
+                false,
+                rest[..len].matches('\n').count(),
+            ));
+            i += len;
+            continue;
+        }
+
+        let at_boundary = src[..i].chars().next_back().is_none_or(char::is_whitespace);
+        if (at_boundary || !syntax.line_needs_space)
+            && syntax.line.iter().any(|leader| rest.starts_with(leader))
+        {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        if c == '\n' {
+            inside.push(false);
+        }
+        i += c.len_utf8();
+    }
+
+    inside
+}
+
+/// With `escape_outside`, the length of a backslash at the start of `rest`
+/// plus the character it escapes (a line continuation is just the
+/// backslash, so the newline still ends the line).
+fn escaped_len(rest: &str, syntax: &CommentSyntax) -> Option<usize> {
+    if !syntax.escape_outside || !rest.starts_with('\\') {
+        return None;
+    }
+    let next = rest[1..].chars().next().filter(|&c| c != '\n');
+    Some(1 + next.map_or(0, char::len_utf8))
+}
+
+/// Length of the block comment at the start of `rest` (which begins with
+/// `open`), including the closing delimiter. Unterminated comments run to
+/// the end of the input.
+fn block_comment_len(rest: &str, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 1;
+    let mut i = open.len();
+
+    while i < rest.len() {
+        let tail = &rest[i..];
+        if tail.starts_with(close) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return i;
+            }
+        } else if nested && tail.starts_with(open) {
+            depth += 1;
+            i += open.len();
+        } else {
+            i += tail.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    rest.len()
+}
+
+/// If a string literal starts at `rest`, return its length in bytes.
+/// `before` is the source preceding `rest`.
+pub(crate) fn match_string(before: &str, rest: &str, strings: &[StringSyntax]) -> Option<usize> {
+    let after_ident = before
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_');
+
+    for form in strings {
+        let len = match form {
+            StringSyntax::Quoted {
+                open,
+                close,
+                escape,
+                multiline,
+                doubled,
+            } => rest
+                .starts_with(open.as_str())
+                .then(|| quoted_len(rest, open, close, *escape, *multiline, *doubled)),
+            StringSyntax::RustRaw if !after_ident => rust_raw_len(rest),
+            StringSyntax::RustChar => rust_char_len(rest),
+            StringSyntax::CppRaw => cpp_raw_len(rest),
+            StringSyntax::LuaLong => lua_long_len(rest),
+            StringSyntax::PyFString if !after_ident => py_fstring_len(rest),
+            StringSyntax::JsRegex if regex_allowed(before) => js_regex_len(rest),
+            StringSyntax::Heredoc => heredoc_len(rest),
+            _ => None,
+        };
+
+        if len.is_some() {
+            return len;
+        }
+    }
+
+    None
+}
+
+fn quoted_len(
+    rest: &str,
+    open: &str,
+    close: &str,
+    escape: Option<char>,
+    multiline: bool,
+    doubled: bool,
+) -> usize {
+    let mut i = open.len();
+
+    while i < rest.len() {
+        let tail = &rest[i..];
+        let c = tail.chars().next().unwrap_or_default();
+
+        if Some(c) == escape {
+            i += c.len_utf8();
+            i += rest[i..].chars().next().map_or(0, char::len_utf8);
+        } else if let Some(after) = tail.strip_prefix(close) {
+            if !(doubled && after.starts_with(close)) {
+                return i + close.len();
+            }
+            i += 2 * close.len();
+        } else if c == '\n' && !multiline {
+            return i;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+
+    rest.len()
+}
+
+/// `r"..."`, `r#"..."#`, `br"..."`, `cr"..."`.
+fn rust_raw_len(rest: &str) -> Option<usize> {
+    let start = if rest.starts_with("br") || rest.starts_with("cr") {
+        2
+    } else if rest.starts_with('r') {
+        1
+    } else {
+        return None;
+    };
+
+    let hashes = rest[start..].bytes().take_while(|&b| b == b'#').count();
+    let body = start + hashes;
+    if !rest[body..].starts_with('"') {
+        return None;
+    }
+
+    let close = format!("\"{}", "#".repeat(hashes));
+    Some(match rest[body + 1..].find(&close) {
+        Some(pos) => body + 1 + pos + close.len(),
+        None => rest.len(),
+    })
+}
+
+/// `'a'`, `'\n'`, `'\u{1F600}'` - but not lifetimes like `'a` or `'static`.
+fn rust_char_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices();
+    if chars.next()?.1 != '\'' {
+        return None;
+    }
+
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        // Escaped char: skip the escaped character, then scan to the closing
+        // quote on this line.
+        let after = 2 + rest[2..].chars().next()?.len_utf8();
+        let end = after + rest[after..].find(['\'', '\n'])?;
+        return (rest.as_bytes()[end] == b'\'').then_some(end + 1);
+    }
+
+    let (idx, second) = chars.next()?;
+    (second == '\'' && first != '\'').then_some(idx + 1)
+}
+
+/// `<<EOF ... EOF`, also `<<-`, `<<~` and a quoted delimiter. The rest of the
+/// opening line is included, and the closing line may be indented.
+/// Unterminated here-documents run to the end of the input.
+fn heredoc_len(rest: &str) -> Option<usize> {
+    let spec = rest.strip_prefix("<<")?;
+    let spec = spec.strip_prefix(['-', '~']).unwrap_or(spec);
+    let spec = spec.trim_start_matches([' ', '\t']);
+    let spec = spec.strip_prefix(['\'', '"']).unwrap_or(spec);
+
+    let end = spec
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(spec.len());
+    let delim = &spec[..end];
+    // `1 << 2` is a shift, `<<<` a here-string.
+    if !delim.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        return None;
+    }
+
+    let mut pos = rest.find('\n')? + 1;
+    for line in rest[pos..].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if content.trim() == delim {
+            return Some(pos + content.len());
+        }
+        pos += line.len();
+    }
+
+    Some(rest.len())
+}
+
+/// `R"delim(...)delim"`.
+fn cpp_raw_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix("R\"")?;
+    let paren = body.find('(')?;
+    let delim = &body[..paren];
+    if delim.len() > 16 || delim.contains(|c: char| c.is_whitespace() || c == '\\' || c == ')') {
+        return None;
+    }
+
+    let close = format!("){delim}\"");
+    let start = 2 + paren + 1;
+    Some(match rest[start..].find(&close) {
+        Some(pos) => start + pos + close.len(),
+        None => rest.len(),
+    })
+}
+
+/// `[[...]]`, `[=[...]=]`, `[==[...]==]`.
+fn lua_long_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix('[')?;
+    let level = body.bytes().take_while(|&b| b == b'=').count();
+    if !body[level..].starts_with('[') {
+        return None;
+    }
+
+    let close = format!("]{}]", "=".repeat(level));
+    let start = 1 + level + 1;
+    Some(match rest[start..].find(&close) {
+        Some(pos) => start + pos + close.len(),
+        None => rest.len(),
+    })
+}
+
+/// Python f-strings with an `f`, `rf` or `fr` prefix (any case).
+///
+/// Replacement fields may contain any expression, including strings that
+/// reuse the outer quote (Python 3.12+) and a format spec after `:`, which is
+/// literal text apart from nested `{...}` fields.
+fn py_fstring_len(rest: &str) -> Option<usize> {
+    enum Field {
+        /// Inside `{...}`, with the given `([{` nesting.
+        Expr(usize),
+        /// After the `:` of a field.
+        Spec,
+    }
+
+    let prefix = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+    let raw = match rest[..prefix].to_ascii_lowercase().as_str() {
+        "f" => false,
+        "rf" | "fr" => true,
+        _ => return None,
+    };
+
+    let body = &rest[prefix..];
+    let quote = ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|q| body.starts_with(q))?;
+
+    let mut fields: Vec<Field> = Vec::new();
+    let mut i = prefix + quote.len();
+
+    while i < rest.len() {
+        let tail = &rest[i..];
+        let c = tail.chars().next().unwrap_or_default();
+
+        match fields.last_mut() {
+            None => {
+                if c == '\\' && !raw {
+                    i += 1 + tail[1..].chars().next().map_or(0, char::len_utf8);
+                    continue;
+                } else if tail.starts_with(quote) {
+                    return Some(i + quote.len());
+                } else if c == '\n' && quote.len() == 1 {
+                    return Some(i);
+                } else if tail.starts_with("{{") || tail.starts_with("}}") {
+                    i += 2;
+                    continue;
+                } else if c == '{' {
+                    fields.push(Field::Expr(0));
+                }
+            }
+            Some(Field::Expr(depth)) => {
+                if c == '"' || c == '\'' || c.is_ascii_alphabetic() {
+                    let after_ident = rest[..i]
+                        .chars()
+                        .next_back()
+                        .is_some_and(|p| p.is_alphanumeric() || p == '_');
+                    let nested = if after_ident {
+                        None
+                    } else {
+                        py_fstring_len(tail).or_else(|| py_plain_string_len(tail))
+                    };
+                    if let Some(len) = nested {
+                        i += len;
+                        continue;
+                    }
+                }
+
+                match c {
+                    '(' | '[' | '{' => *depth += 1,
+                    ')' | ']' if *depth > 0 => *depth -= 1,
+                    '}' if *depth > 0 => *depth -= 1,
+                    '}' => {
+                        fields.pop();
+                    }
+                    ':' if *depth == 0 => *fields.last_mut()? = Field::Spec,
+                    _ => {}
+                }
+            }
+            Some(Field::Spec) => match c {
+                '{' => fields.push(Field::Expr(0)),
+                '}' => {
+                    fields.pop();
+                }
+                _ => {}
+            },
+        }
+
+        i += c.len_utf8();
+    }
+
+    Some(rest.len())
+}
+
+/// A non-f Python string inside an f-string replacement field.
+fn py_plain_string_len(rest: &str) -> Option<usize> {
+    let quote = ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|q| rest.starts_with(q))?;
+    Some(quoted_len(
+        rest,
+        quote,
+        quote,
+        Some('\\'),
+        quote.len() == 3,
+        false,
+    ))
+}
+
+/// Whether a `/` after `before` starts a JavaScript regex literal rather than
+/// a division, judging by the previous token.
+fn regex_allowed(before: &str) -> bool {
+    let line = before.rsplit('\n').next().unwrap_or_default().trim_end();
+
+    match line.chars().next_back() {
+        None => true,
+        Some(c) if "(,=:[!&|?{};+-*%<>~^".contains(c) => true,
+        Some(c) if c.is_alphanumeric() || c == '_' || c == '$' => {
+            let word_start = line
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .map_or(0, |pos| pos + 1);
+            matches!(
+                &line[word_start..],
+                "return"
+                    | "typeof"
+                    | "case"
+                    | "in"
+                    | "of"
+                    | "void"
+                    | "yield"
+                    | "await"
+                    | "delete"
+                    | "throw"
+                    | "new"
+                    | "else"
+                    | "do"
+            )
+        }
+        Some(_) => false,
+    }
+}
+
+/// `/pattern/flags`, on a single line. `None` if `rest` is a comment or the
+/// literal is not closed on its line.
+fn js_regex_len(rest: &str) -> Option<usize> {
+    if !rest.starts_with('/') || rest.starts_with("//") || rest.starts_with("/*") {
+        return None;
+    }
+
+    let mut in_class = false;
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return None,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                let flags = rest[i + 1..]
+                    .bytes()
+                    .take_while(u8::is_ascii_alphabetic)
+                    .count();
+                return Some(i + 1 + flags);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Whether a `//` after `before` is part of a URL (`http://`), which C++
+/// happens to accept as a label followed by a comment.
+fn is_url_scheme(before: &str) -> bool {
+    before
+        .strip_suffix(':')
+        .and_then(|head| head.chars().next_back())
+        .is_some_and(|c| c.is_ascii_alphabetic())
+}
+
+/// Drop spaces and tabs at the end of `out`'s current line.
+fn trim_trailing_blanks(out: &mut String) {
+    let trimmed = out.trim_end_matches([' ', '\t']).len();
+    out.truncate(trimmed);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn syntax(ext: &str) -> Option<&'static CommentSyntax> {
+        Registry::builtin()
+            .for_ext(ext)
+            .and_then(|lang| lang.comments.as_ref())
+    }
+
     #[test]
     fn strip_comments_py_removes_full_line_hash_comments_and_blanks() {
         let src = r#"
@@ -66,4 +816,320 @@ print("world")
         let expected = "  # not a comment for unknown\nx\n";
         assert_eq!(out, expected);
     }
+
+    #[test]
+    fn strip_comment_lines_keeps_original_line_numbers() {
+        let src = "// header\n\nfn main() {\n    // body\n}\n";
+        let out = strip_comment_lines(src, syntax("rs"), &KeepList::default());
+        assert_eq!(out, vec![(3, "fn main() {"), (5, "}")]);
+    }
+
+    fn blocks(src: &str, ext: &str) -> String {
+        strip_comments(src, syntax(ext), StripLevel::Blocks, &KeepList::default())
+            .into_iter()
+            .map(|(_, line)| line + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn blocks_removes_c_style_block_comments_and_keeps_line_numbers() {
+        let src = "/*\n * License\n */\nint x = 1; /* one */\nint y/* */= 2;\n";
+        let out = strip_comments(src, syntax("c"), StripLevel::Blocks, &KeepList::default());
+        assert_eq!(
+            out,
+            vec![(4, "int x = 1;".to_string()), (5, "int y = 2;".to_string())]
+        );
+    }
+
+    #[test]
+    fn blocks_handles_nested_rust_comments() {
+        let src = "/* outer /* inner */ still comment */\nfn main() {}\n";
+        assert_eq!(blocks(src, "rs"), "fn main() {}\n");
+
+        // C does not nest: the first `*/` ends the comment.
+        let src = "/* a /* b */ int x; /* c */\n";
+        assert_eq!(blocks(src, "c"), "int x;\n");
+    }
+
+    #[test]
+    fn blocks_respects_string_literals() {
+        let src = r##"let a = "/* not a comment */"; // trailing stays
+let b = r#"// still a string "# ;
+let c = '/';
+fn f<'a>(x: &'a str) -> &'a str { x } /* gone */
+let d = '\'';/* gone */
+"##;
+        let expected = r##"let a = "/* not a comment */"; // trailing stays
+let b = r#"// still a string "# ;
+let c = '/';
+fn f<'a>(x: &'a str) -> &'a str { x }
+let d = '\'';
+"##;
+        assert_eq!(blocks(src, "rs"), expected);
+    }
+
+    #[test]
+    fn blocks_skips_raw_and_template_strings() {
+        let src = "auto s = R\"x(/* keep */)x\";\n";
+        assert_eq!(blocks(src, "cpp"), src);
+
+        let src = "const t = `line\n/* keep */`;\n/* drop */\n";
+        assert_eq!(blocks(src, "js"), "const t = `line\n/* keep */`;\n");
+    }
+
+    #[test]
+    fn blocks_removes_lua_and_html_block_comments() {
+        let src = "--[[ header\nmore ]]\nlocal s = [[ --[[ kept ]]\n-- line\nprint(s)\n";
+        assert_eq!(blocks(src, "lua"), "local s = [[ --[[ kept ]]\nprint(s)\n");
+
+        let src = "<!-- a\nb -->\n<p>hi</p><!-- c -->\n";
+        assert_eq!(blocks(src, "html"), "<p>hi</p>\n");
+    }
+
+    #[test]
+    fn blocks_keeps_hash_inside_shell_words() {
+        let src = "# comment\necho ${#arr[@]} \"# not\" # trailing\n";
+        assert_eq!(blocks(src, "sh"), "echo ${#arr[@]} \"# not\" # trailing\n");
+    }
+
+    #[test]
+    fn blocks_ignores_hash_lines_inside_python_strings() {
+        let src = "s = \"\"\"\n# inside string\n\"\"\"\n# real comment\n";
+        assert_eq!(blocks(src, "py"), "s = \"\"\"\n# inside string\n\"\"\"\n");
+    }
+
+    /// `(ext, source, expected)` cases for `--strip-comments=aggressive`.
+    /// Each source mixes trailing comments with lookalikes that must survive.
+    const AGGRESSIVE_CORPUS: &[(&str, &str, &str)] = &[
+        // Rust: strings, raw strings, chars, lifetimes, URLs in strings.
+        (
+            "rs",
+            r##"let url = "http://example.com"; // homepage
+let raw = r#"a // b "# ; // raw
+let c = '"'; let h = '#'; // chars
+fn f<'a>(x: &'a str) -> &'a str { x } // lifetime
+let esc = "quote \" // still string"; // gone
+"##,
+            r##"let url = "http://example.com";
+let raw = r#"a // b "# ;
+let c = '"'; let h = '#';
+fn f<'a>(x: &'a str) -> &'a str { x }
+let esc = "quote \" // still string";
+"##,
+        ),
+        // C / C++: char literals, raw strings, unquoted URL labels.
+        (
+            "cpp",
+            r#"char q = '"'; // quote char
+auto r = R"(// not a comment)"; // raw
+int x = a/b; // divide
+http://example.com
+"#,
+            r#"char q = '"';
+auto r = R"(// not a comment)";
+int x = a/b;
+http://example.com
+"#,
+        ),
+        // Go: backtick raw strings.
+        (
+            "go",
+            "s := `// raw\nstill raw` // trailing\nu := \"https://go.dev\" // link\n",
+            "s := `// raw\nstill raw`\nu := \"https://go.dev\"\n",
+        ),
+        // JavaScript: templates, regex literals, division.
+        (
+            "js",
+            r#"const t = `${a} // in template`; // gone
+const re = /https?:\/\//g; // regex
+const half = total / 2; // divide
+if (/\/\//.test(s)) run(); // regex after paren
+"#,
+            r#"const t = `${a} // in template`;
+const re = /https?:\/\//g;
+const half = total / 2;
+if (/\/\//.test(s)) run();
+"#,
+        ),
+        // Python: f-strings with nested quotes and `#` format specs.
+        (
+            "py",
+            r##"x = "#not" + '#nor'  # comment
+y = f"{d['#']}"  # fstring
+z = f"{d["k"]:#x}"  # nested same quote
+w = rf'\d+#{n}'  # raw fstring
+v = "http://x"  # url
+"##,
+            r##"x = "#not" + '#nor'
+y = f"{d['#']}"
+z = f"{d["k"]:#x}"
+w = rf'\d+#{n}'
+v = "http://x"
+"##,
+        ),
+        // Shell: `#` only starts a comment after whitespace.
+        (
+            "sh",
+            "echo ${#arr[@]} ${v#pre} # count\ncurl http://x/#frag 'a # b' # fetch\n",
+            "echo ${#arr[@]} ${v#pre}\ncurl http://x/#frag 'a # b'\n",
+        ),
+        // Shell: backslash escapes outside strings, here-document bodies.
+        (
+            "sh",
+            "echo don\\'t # c\ny=1 # c\nz='a # b' \\# lit # c\ncat <<-'EOF' | sort\n\tvalue # keep me\n\tEOF\necho $((1 << 2)) # shift\n",
+            "echo don\\'t\ny=1\nz='a # b' \\# lit\ncat <<-'EOF' | sort\n\tvalue # keep me\n\tEOF\necho $((1 << 2))\n",
+        ),
+        // Ruby: interpolation and `#` inside strings.
+        (
+            "rb",
+            "puts \"#{name} # hi\" # greet\nx = '#' # hash\n",
+            "puts \"#{name} # hi\"\nx = '#'\n",
+        ),
+        // YAML: `#` inside values without a leading space is not a comment.
+        (
+            "yaml",
+            "color: \"#fff\" # white\nurl: http://x/#top # link\nkey: a#b\n",
+            "color: \"#fff\"\nurl: http://x/#top\nkey: a#b\n",
+        ),
+        // TOML: literal strings.
+        (
+            "toml",
+            "path = 'C:\\dir#1' # windows\nname = \"a # b\" # name\n",
+            "path = 'C:\\dir#1'\nname = \"a # b\"\n",
+        ),
+        // SQL: `--` in strings.
+        (
+            "sql",
+            "SELECT '--not' AS a, b -- trailing\nFROM t; -- done\n",
+            "SELECT '--not' AS a, b\nFROM t;\n",
+        ),
+        // Lua: long strings.
+        (
+            "lua",
+            "local s = [[ -- kept ]] -- gone\nlocal t = \"--\" -- dash\n",
+            "local s = [[ -- kept ]]\nlocal t = \"--\"\n",
+        ),
+    ];
+
+    #[test]
+    fn aggressive_removes_trailing_comments_across_languages() {
+        for (ext, src, expected) in AGGRESSIVE_CORPUS {
+            let out: String = strip_comments(
+                src,
+                syntax(ext),
+                StripLevel::Aggressive,
+                &KeepList::default(),
+            )
+            .into_iter()
+            .map(|(_, line)| line + "\n")
+            .collect();
+            assert_eq!(out, *expected, "ext: {ext}");
+        }
+    }
+
+    #[test]
+    fn aggressive_keeps_line_numbers() {
+        let src = "/* header */\nx = 1; // one\n// two\ny = 2;\n";
+        let out = strip_comments(
+            src,
+            syntax("c"),
+            StripLevel::Aggressive,
+            &KeepList::default(),
+        );
+        assert_eq!(
+            out,
+            vec![(2, "x = 1;".to_string()), (4, "y = 2;".to_string())]
+        );
+    }
+
+    #[test]
+    fn default_keep_list_preserves_docs_and_pragmas() {
+        let keep = KeepList::default();
+        let src = "#!/usr/bin/env python\n# -*- coding: utf-8 -*-\n# plain\nimport x  # type: ignore\ny = 1  # note\n";
+        let out: Vec<String> = strip_comments(src, syntax("py"), StripLevel::Aggressive, &keep)
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(
+            out,
+            vec![
+                "#!/usr/bin/env python",
+                "# -*- coding: utf-8 -*-",
+                "import x  # type: ignore",
+                "y = 1",
+            ]
+        );
+
+        let src = "//! Crate docs.\n// plain\n/// Item docs.\nfn f() {\n    // SAFETY: checked above.\n    unsafe { g() }\n}\n";
+        let out = strip_comment_lines(src, syntax("rs"), &keep);
+        assert_eq!(
+            out.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            vec![1, 3, 4, 5, 6, 7]
+        );
+
+        let src = "/*! License */\n/* gone */\n// @ts-ignore\nlet a = 1; // prettier-ignore\n";
+        assert_eq!(
+            strip_comments(src, syntax("ts"), StripLevel::Aggressive, &keep)
+                .into_iter()
+                .map(|(_, line)| line)
+                .collect::<Vec<_>>(),
+            vec![
+                "/*! License */",
+                "// @ts-ignore",
+                "let a = 1; // prettier-ignore"
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_list_is_configurable() -> Result<()> {
+        let keep = KeepList::new(&["^// TODO".to_string()], false)?;
+        let src = "// TODO: fix\n/// docs\nfn f() {}\n";
+        let out: Vec<(usize, &str)> = strip_comment_lines(src, syntax("rs"), &keep);
+        assert_eq!(out, vec![(1, "// TODO: fix"), (3, "fn f() {}")]);
+
+        assert!(KeepList::new(&["(".to_string()], true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn registry_languages_strip_with_their_own_syntax() {
+        let aggressive = |src: &str, ext: &str| -> String {
+            strip_comments(
+                src,
+                syntax(ext),
+                StripLevel::Aggressive,
+                &KeepList::default(),
+            )
+            .into_iter()
+            .map(|(_, line)| line + "\n")
+            .collect()
+        };
+
+        let src = "{-# LANGUAGE GADTs #-}\n{- outer {- inner -} -}\nmain = print 'x' -- greet\n";
+        assert_eq!(
+            aggressive(src, "hs"),
+            "{-# LANGUAGE GADTs #-}\nmain = print 'x'\n"
+        );
+
+        let src = "#= a #= b =# c =#\nx = \"#\" # note\n";
+        assert_eq!(aggressive(src, "jl"), "x = \"#\"\n");
+
+        // Trailing markers are data in INI files and Vim strings.
+        let src = "; comment\nurl = http://x ; not stripped\n";
+        assert_eq!(aggressive(src, "ini"), "url = http://x ; not stripped\n");
+
+        let src = "\" comment\necho \"hi\"\n";
+        assert_eq!(aggressive(src, "vim"), "echo \"hi\"\n");
+
+        // Sass and Less have `//` line comments; unquoted `url()` keeps its `//`.
+        for ext in ["scss", "less"] {
+            let src = "// theme\n$w: 1px; // width\na { b: url(//cdn.example/x.png); }\n/* c */\n";
+            assert_eq!(
+                aggressive(src, ext),
+                "$w: 1px;\na { b: url(//cdn.example/x.png); }\n",
+                "ext: {ext}"
+            );
+        }
+    }
 }
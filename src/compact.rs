@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::comments::lines_in_strings;
+use crate::lang::Language;
+
+/// Tidy whitespace for `--compact`, keeping each line's original number.
+///
+/// - Trailing whitespace is removed.
+/// - Runs of blank lines become one; blank lines at the start and end go.
+/// - For languages where indentation is not significant, each indentation
+///   level becomes a single space (tabs count as one level each). Files of
+///   unknown languages or with `significant_indent` keep their indentation.
+///
+/// Lines inside multi-line string literals are left exactly as they are.
+pub fn compact(lines: Vec<(usize, String)>, language: Option<&Language>) -> Vec<(usize, String)> {
+    let verbatim = match language.and_then(|lang| lang.comments.as_ref()) {
+        Some(syntax) => {
+            let text: String = lines.iter().map(|(_, line)| format!("{line}\n")).collect();
+            lines_in_strings(&text, syntax)
+        }
+        None => Vec::new(),
+    };
+    let is_verbatim = |idx: usize| verbatim.get(idx).copied().unwrap_or(false);
+
+    let reindent = language.is_some_and(|lang| !lang.significant_indent);
+    let unit = if reindent {
+        indent_unit(
+            lines
+                .iter()
+                .enumerate()
+                .filter(|(idx, (_, line))| !is_verbatim(*idx) && !line.trim().is_empty())
+                .filter_map(|(_, (_, line))| indent(line)),
+        )
+    } else {
+        1
+    };
+
+    let mut out: Vec<(usize, String)> = Vec::with_capacity(lines.len());
+
+    for (idx, (n, line)) in lines.into_iter().enumerate() {
+        if is_verbatim(idx) {
+            out.push((n, line));
+            continue;
+        }
+
+        // The line break ends a string literal, so trailing spaces are content.
+        let line = if is_verbatim(idx + 1) {
+            line
+        } else {
+            line.trim_end().to_string()
+        };
+
+        if line.is_empty() {
+            if out.last().is_some_and(|(_, prev)| !prev.is_empty()) {
+                out.push((n, line));
+            }
+            continue;
+        }
+
+        match indent(&line).filter(|_| reindent) {
+            Some((tabs, spaces)) => {
+                let levels = tabs + spaces.div_ceil(unit);
+                let body = &line[tabs + spaces..];
+                out.push((n, format!("{}{body}", " ".repeat(levels))));
+            }
+            None => out.push((n, line)),
+        }
+    }
+
+    if out.last().is_some_and(|(_, line)| line.is_empty()) {
+        out.pop();
+    }
+
+    out
+}
+
+/// Leading tabs and spaces of `line`, if it is indented with tabs followed
+/// by spaces (the usual mixed style) rather than an arbitrary mix.
+fn indent(line: &str) -> Option<(usize, usize)> {
+    let tabs = line.bytes().take_while(|&b| b == b'\t').count();
+    let spaces = line[tabs..].bytes().take_while(|&b| b == b' ').count();
+    let next = line[tabs + spaces..].bytes().next();
+    (next != Some(b'\t')).then_some((tabs, spaces))
+}
+
+/// The number of spaces per indentation level: the most common increase in
+/// indentation from one line to the next (ties go to the smaller one).
+/// Alignment such as ` * ` in block comments is rare enough not to win.
+fn indent_unit(indents: impl Iterator<Item = (usize, usize)>) -> usize {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    let mut prev = 0;
+
+    for (tabs, spaces) in indents {
+        if tabs == 0 {
+            if spaces > prev {
+                *counts.entry(spaces - prev).or_default() += 1;
+            }
+            prev = spaces;
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|&(step, count)| (count, std::cmp::Reverse(step)))
+        .map_or(1, |(step, _)| step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::Registry;
+
+    fn run(src: &str, ext: &str) -> String {
+        let lines = src
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.to_string()))
+            .collect();
+        compact(lines, Registry::builtin().for_ext(ext))
+            .into_iter()
+            .map(|(_, line)| line + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn reindents_brace_languages_to_one_space_per_level() {
+        let src = "\n\nfn main() {   \n    if x {\n        y();\n\n\n\n    }\n    /**\n     * Doc.\n     */\n}\n\n";
+        let expected = "fn main() {\n if x {\n  y();\n\n }\n /**\n  * Doc.\n  */\n}\n";
+        assert_eq!(run(src, "rs"), expected);
+
+        let src = "int main() {\n\tif (x) {\n\t\treturn 1;\n\t}\n}\n";
+        let expected = "int main() {\n if (x) {\n  return 1;\n }\n}\n";
+        assert_eq!(run(src, "c"), expected);
+    }
+
+    #[test]
+    fn keeps_indentation_for_significant_languages() {
+        let src = "def f():  \n    if x:\n        return 1\n\n\n\n\ndef g():\n\tpass\n";
+        let expected = "def f():\n    if x:\n        return 1\n\ndef g():\n\tpass\n";
+        assert_eq!(run(src, "py"), expected);
+
+        let src = "a:\n  b:\n    - c   \n\n\n  d: 1\n";
+        assert_eq!(run(src, "yaml"), "a:\n  b:\n    - c\n\n  d: 1\n");
+
+        // Unknown languages only lose trailing whitespace and blank lines.
+        assert_eq!(run("a  \n\n\n    b\n", "txt"), "a\n\n    b\n");
+    }
+
+    #[test]
+    fn leaves_multiline_strings_alone() {
+        let src = "fn f() {\n    let s = \"a  \n\n\n        b\";\n    g();\n}\n";
+        let expected = "fn f() {\n let s = \"a  \n\n\n        b\";\n g();\n}\n";
+        assert_eq!(run(src, "rs"), expected);
+
+        let src = "def f():\n    return \"\"\"\n\n\n    x  \n\"\"\"\n";
+        assert_eq!(run(src, "py"), src);
+
+        let src =
+            "resource \"x\" \"y\" {\n  policy = <<-EOT\n    {\n      \"a\": 1\n    }\n  EOT\n}\n";
+        let expected =
+            "resource \"x\" \"y\" {\n policy = <<-EOT\n    {\n      \"a\": 1\n    }\n  EOT\n}\n";
+        assert_eq!(run(src, "tf"), expected);
+
+        let src = "class C {\n    string a = @\"x \"\"q\"\"\n        y\";\n    string b = \"\"\"\n        z\n        \"\"\";\n}\n";
+        let expected = "class C {\n string a = @\"x \"\"q\"\"\n        y\";\n string b = \"\"\"\n        z\n        \"\"\";\n}\n";
+        assert_eq!(run(src, "cs"), expected);
+    }
+
+    #[test]
+    fn partial_indents_round_up_to_the_next_level() {
+        let src = "f() {\n    if (x) {\n        a;\n       b;\n    }\n}\n";
+        assert_eq!(run(src, "c"), "f() {\n if (x) {\n  a;\n  b;\n }\n}\n");
+    }
+}
@@ -0,0 +1,243 @@
+use crate::comments::{StringSyntax, match_string};
+use crate::lang::Registry;
+
+/// Whether `--strip-docstrings` applies to files of the registry language
+/// `name`.
+pub fn applies_to(name: &str) -> bool {
+    name == "python"
+}
+
+/// Where the next statement would be a docstring.
+#[derive(Debug, Clone, Copy)]
+enum Expect {
+    /// First statement of the module.
+    Module,
+    /// First statement of a `def` / `class` body; holds the header's indent.
+    Body(usize),
+}
+
+/// Remove Python module, class and function docstrings.
+///
+/// A docstring is a plain (non-f, non-bytes) string literal that is the first
+/// statement of a module or a `def` / `class` body, alone on its line.
+/// Strings that are assigned, passed as arguments or continued by an
+/// operator are left alone. A body that held nothing but its docstring gets
+/// `...` so it stays valid Python.
+///
+/// Newlines are kept, so the output has exactly as many lines as `src`;
+/// lines that only held a docstring are left blank.
+pub fn strip_docstrings(src: &str) -> String {
+    let strings = Registry::builtin()
+        .for_ext("py")
+        .and_then(|lang| lang.comments.as_ref())
+        .map(|syntax| syntax.strings.as_slice())
+        .unwrap_or_default();
+
+    let mut out = String::with_capacity(src.len());
+    let mut i = 0;
+    let mut expect = Some(Expect::Module);
+    // Start of a logical line: not inside brackets or after a `\` continuation.
+    let mut line_start = true;
+    let mut depth = 0usize;
+    // Indent of the current logical line, if it opens a `def` / `class`.
+    let mut header: Option<usize> = None;
+    // Last character of the current logical line outside strings and comments.
+    let mut last = ' ';
+
+    while i < src.len() {
+        if line_start {
+            line_start = false;
+
+            let line_end = src[i..].find('\n').map_or(src.len(), |pos| i + pos);
+            let line = &src[i..line_end];
+            let body = line.trim_start();
+            let indent = line.len() - body.len();
+
+            if body.is_empty() || body.starts_with('#') {
+                out.push_str(line);
+                if line_end < src.len() {
+                    out.push('\n');
+                }
+                i = line_end + 1;
+                line_start = true;
+                continue;
+            }
+
+            let expected = expect.take();
+            let allowed = match expected {
+                Some(Expect::Module) => true,
+                Some(Expect::Body(outer)) => indent > outer,
+                None => false,
+            };
+            if allowed && let Some(len) = docstring_len(&src[i + indent..], strings) {
+                let doc_end = i + indent + len;
+                if let Some(Expect::Body(outer)) = expected
+                    && body_is_empty(&src[doc_end..], outer)
+                {
+                    out.push_str(&line[..indent]);
+                    out.push_str("...");
+                }
+                out.extend(std::iter::repeat_n(
+                    '\n',
+                    src[i..doc_end].matches('\n').count(),
+                ));
+
+                // Drop the rest of the line (whitespace or a comment).
+                i = src[doc_end..]
+                    .find('\n')
+                    .map_or(src.len(), |pos| doc_end + pos);
+                if i < src.len() {
+                    out.push('\n');
+                    i += 1;
+                }
+                line_start = true;
+                continue;
+            }
+
+            header = ["def ", "async def ", "class "]
+                .iter()
+                .any(|kw| body.starts_with(kw))
+                .then_some(indent);
+            last = ' ';
+        }
+
+        let rest = &src[i..];
+        if let Some(len) = match_string(&src[..i], rest, strings) {
+            out.push_str(&rest[..len]);
+            last = '"';
+            i += len;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        match c {
+            '#' => {
+                let len = rest.find('\n').unwrap_or(rest.len());
+                out.push_str(&rest[..len]);
+                i += len;
+                continue;
+            }
+            '\\' if rest[1..].starts_with('\n') => {
+                out.push_str("\\\n");
+                i += 2;
+                continue;
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            '\n' if depth == 0 => {
+                if let Some(indent) = header.take()
+                    && last == ':'
+                {
+                    expect = Some(Expect::Body(indent));
+                }
+                line_start = true;
+            }
+            _ => {}
+        }
+
+        if !c.is_whitespace() {
+            last = c;
+        }
+        out.push(c);
+        i += c.len_utf8();
+    }
+
+    out
+}
+
+/// Length of the docstring at the start of `rest`: a plain string literal
+/// followed only by whitespace or a comment on its closing line.
+fn docstring_len(rest: &str, strings: &[StringSyntax]) -> Option<usize> {
+    let prefix = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+    if !matches!(rest[..prefix].to_ascii_lowercase().as_str(), "" | "r" | "u") {
+        return None;
+    }
+
+    let len = prefix + match_string("", &rest[prefix..], strings)?;
+    let after = rest[len..].trim_start_matches([' ', '\t']);
+    (after.is_empty() || after.starts_with(['\n', '\r', '#'])).then_some(len)
+}
+
+/// Whether the body of a header at indent `outer` has no statements left
+/// after `rest`, the source following its docstring.
+fn body_is_empty(rest: &str, outer: usize) -> bool {
+    rest.lines()
+        .skip(1)
+        .map(|line| (line.len() - line.trim_start().len(), line.trim_start()))
+        .find(|(_, body)| !body.is_empty() && !body.starts_with('#'))
+        .is_none_or(|(indent, _)| indent <= outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_module_class_and_function_docstrings() {
+        let src = r#""""Module docs."""
+import os
+
+
+class Greeter:
+    """Greets people.
+
+    Longer description.
+    """
+
+    def greet(self, name):
+        '''Say hello.'''
+        return f"hello {name}"
+"#;
+        let expected = r#"
+import os
+
+
+class Greeter:
+
+
+
+
+
+    def greet(self, name):
+
+        return f"hello {name}"
+"#;
+        assert_eq!(strip_docstrings(src), expected);
+    }
+
+    #[test]
+    fn keeps_assigned_and_argument_strings() {
+        let src = r#"def f():
+    x = """not a docstring"""
+    print("""also kept""")
+    return x
+
+
+def g():
+    """Doc.""" + suffix
+"#;
+        assert_eq!(strip_docstrings(src), src);
+    }
+
+    #[test]
+    fn only_the_first_statement_is_a_docstring() {
+        let src = "def f():\n    pass\n    \"\"\"kept\"\"\"\n";
+        assert_eq!(strip_docstrings(src), src);
+
+        let src = "x = 1\n\"\"\"kept\"\"\"\n";
+        assert_eq!(strip_docstrings(src), src);
+    }
+
+    #[test]
+    fn empty_bodies_get_an_ellipsis() {
+        let src = "class Marker:\n    \"\"\"Only docs.\"\"\"\n\n\ndef f(\n    a,\n) -> int:\n    r\"\"\"Raw.\"\"\"\n";
+        let expected = "class Marker:\n    ...\n\n\ndef f(\n    a,\n) -> int:\n    ...\n";
+        assert_eq!(strip_docstrings(src), expected);
+    }
+
+    #[test]
+    fn ignores_headers_inside_strings_and_fstrings() {
+        let src = "s = \"\"\"\ndef f():\n    \"\"\"\nt = f\"{x:}\"\n";
+        assert_eq!(strip_docstrings(src), src);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::comments::CommentSyntax;
+
+/// The built-in table, see `languages.toml` for the format.
+static BUILTIN: LazyLock<Registry> = LazyLock::new(|| {
+    Registry::parse(include_str!("languages.toml")).expect("built-in language table is valid")
+});
+
+/// A language known to `pc`, as described by a `[[language]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Language {
+    /// Used as the Markdown fence info string and the `--meta` language.
+    pub name: String,
+    /// Extensions without the dot, matched case-insensitively.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Exact file names, e.g. `Dockerfile`.
+    #[serde(default)]
+    pub filenames: Vec<String>,
+    /// Shebang interpreters, without version suffixes (`python` also
+    /// matches `python3.12`).
+    #[serde(default)]
+    pub interpreters: Vec<String>,
+    /// Leading whitespace is syntax or string content, so `--compact` must
+    /// keep it.
+    #[serde(default)]
+    pub significant_indent: bool,
+    /// Comment and string syntax for `--strip-comments`, if known.
+    pub comments: Option<CommentSyntax>,
+}
+
+/// A `[[type]]` entry: a `--type` name that combines languages (or other
+/// types) with extra extensions and file names, e.g. `web`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TypeGroup {
+    pub name: String,
+    /// Names of languages or earlier types whose files are included.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub filenames: Vec<String>,
+}
+
+/// Languages keyed by file name and extension: the built-in table, optionally
+/// extended by a user TOML file.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    languages: Vec<Language>,
+    types: Vec<TypeGroup>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegistryFile {
+    #[serde(default)]
+    language: Vec<Language>,
+    #[serde(default, rename = "type")]
+    types: Vec<TypeGroup>,
+}
+
+impl Registry {
+    /// The built-in language table.
+    pub fn builtin() -> &'static Registry {
+        &BUILTIN
+    }
+
+    /// The built-in table plus the languages in `path`, if given. User entries
+    /// replace built-in entries with the same name and take precedence for
+    /// their extensions and file names.
+    pub fn load(path: Option<&Path>) -> Result<Registry> {
+        let Some(path) = path else {
+            return Ok(Registry::builtin().clone());
+        };
+
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read language file {}", path.display()))?;
+        let user = Registry::parse(&text)
+            .with_context(|| format!("Invalid language file {}", path.display()))?;
+
+        Ok(Registry::builtin().clone().extend(user))
+    }
+
+    /// Parse a registry from TOML text.
+    pub fn parse(text: &str) -> Result<Registry> {
+        let file: RegistryFile = toml::from_str(text)?;
+        Ok(Registry {
+            languages: file.language,
+            types: file.types,
+        })
+    }
+
+    /// Every language, user entries first.
+    pub fn languages(&self) -> &[Language] {
+        &self.languages
+    }
+
+    /// The `[[type]]` groups, built-in entries first.
+    pub fn types(&self) -> &[TypeGroup] {
+        &self.types
+    }
+
+    fn extend(self, user: Registry) -> Registry {
+        let builtin: Vec<Language> = self
+            .languages
+            .into_iter()
+            .filter(|lang| !user.languages.iter().any(|own| own.name == lang.name))
+            .collect();
+
+        let mut languages = user.languages;
+        languages.extend(builtin);
+
+        // Later groups may include earlier ones, so user groups go last.
+        let mut types: Vec<TypeGroup> = self
+            .types
+            .into_iter()
+            .filter(|group| !user.types.iter().any(|own| own.name == group.name))
+            .collect();
+        types.extend(user.types);
+
+        Registry { languages, types }
+    }
+
+    /// The language of a file, by [`for_path`](Self::for_path), falling back
+    /// to the shebang line at the start of `contents`.
+    pub fn detect(&self, path: &Path, contents: &[u8]) -> Option<&Language> {
+        self.for_path(path).or_else(|| self.for_shebang(contents))
+    }
+
+    /// The language of `path`, by exact file name first, then by extension.
+    pub fn for_path(&self, path: &Path) -> Option<&Language> {
+        self.for_filename(path)
+            .or_else(|| self.for_ext(path.extension().and_then(|s| s.to_str())?))
+    }
+
+    /// The language whose `filenames` list the file name of `path`.
+    pub fn for_filename(&self, path: &Path) -> Option<&Language> {
+        let name = path.file_name().and_then(|s| s.to_str())?;
+        self.languages
+            .iter()
+            .find(|lang| lang.filenames.iter().any(|f| f == name))
+    }
+
+    /// The language named by a `#!` line at the start of `contents`, e.g.
+    /// `#!/usr/bin/env python3` or `#!/bin/bash -e`.
+    pub fn for_shebang(&self, contents: &[u8]) -> Option<&Language> {
+        let line = contents.strip_prefix(b"#!")?;
+        let line = &line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())];
+        let line = std::str::from_utf8(line).ok()?;
+
+        let mut words = line.split_whitespace();
+        let mut program = words.next()?.rsplit('/').next()?;
+        if program == "env" {
+            // Skip `env` options (`-S`) and variable assignments.
+            program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+        }
+        let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        self.languages
+            .iter()
+            .find(|lang| lang.interpreters.iter().any(|i| i == program))
+    }
+
+    /// The language for a file extension (without the dot).
+    pub fn for_ext(&self, ext: &str) -> Option<&Language> {
+        self.languages
+            .iter()
+            .find(|lang| lang.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_table_has_no_duplicate_keys() {
+        let mut seen = std::collections::HashSet::new();
+        for lang in &Registry::builtin().languages {
+            assert!(seen.insert(lang.name.clone()), "name: {}", lang.name);
+            for ext in &lang.extensions {
+                assert!(seen.insert(format!(".{ext}")), "extension: {ext}");
+            }
+            for file in &lang.filenames {
+                assert!(seen.insert(format!("/{file}")), "file name: {file}");
+            }
+        }
+    }
+
+    #[test]
+    fn for_path_prefers_file_names() {
+        let registry = Registry::builtin();
+        let name = |path: &str| registry.for_path(Path::new(path)).map(|l| l.name.as_str());
+
+        assert_eq!(name("docker/Dockerfile"), Some("dockerfile"));
+        assert_eq!(name("CMakeLists.txt"), Some("cmake"));
+        assert_eq!(name("src/Main.KT"), Some("kotlin"));
+        assert_eq!(name("notes.txt"), None);
+    }
+
+    #[test]
+    fn shebangs_name_the_interpreter() {
+        let registry = Registry::builtin();
+        let name = |src: &str| {
+            registry
+                .for_shebang(src.as_bytes())
+                .map(|l| l.name.as_str())
+        };
+
+        assert_eq!(name("#!/usr/bin/env python3\nimport os\n"), Some("python"));
+        assert_eq!(name("#!/usr/local/bin/python3.12 -u\n"), Some("python"));
+        assert_eq!(name("#!/bin/sh\nset -e\n"), Some("bash"));
+        assert_eq!(
+            name("#!/usr/bin/env -S node --no-warnings\n"),
+            Some("javascript")
+        );
+        assert_eq!(name("#! /usr/bin/env LANG=C ruby\n"), Some("ruby"));
+        assert_eq!(name("#!/usr/bin/make -f\n"), Some("makefile"));
+        assert_eq!(name("#!/usr/bin/env unknown\n"), None);
+        assert_eq!(name("import os\n"), None);
+
+        let detected = registry.detect(Path::new("bin/tool"), b"#!/usr/bin/env python3\n");
+        assert_eq!(detected.map(|l| l.name.as_str()), Some("python"));
+        let detected = registry.detect(Path::new("run.sh"), b"#!/usr/bin/env python3\n");
+        assert_eq!(detected.map(|l| l.name.as_str()), Some("bash"));
+    }
+
+    #[test]
+    fn user_entries_override_and_extend_builtin() -> Result<()> {
+        let user = Registry::parse(
+            r#"
+            [[language]]
+            name = "python"
+            extensions = ["py"]
+
+            [[language]]
+            name = "fennel"
+            extensions = ["fnl", "lua"]
+            [language.comments]
+            line = [";"]
+            strings = [{ kind = "quoted", open = '"', close = '"', escape = '\' }]
+            "#,
+        )?;
+        let registry = Registry::builtin().clone().extend(user);
+
+        assert!(registry.for_ext("py").is_some_and(|l| l.comments.is_none()));
+        assert_eq!(registry.for_ext("pyi").map(|l| l.name.as_str()), None);
+        assert_eq!(
+            registry.for_ext("lua").map(|l| l.name.as_str()),
+            Some("fennel")
+        );
+        assert_eq!(
+            registry.for_ext("rs").map(|l| l.name.as_str()),
+            Some("rust")
+        );
+
+        assert!(Registry::parse("[[language]]\nname = \"x\"\ncolour = 1\n").is_err());
+        Ok(())
+    }
+}
@@ -1,53 +1,364 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fs;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use ignore::{DirEntry, WalkBuilder};
 
+pub mod binary;
+pub mod budget;
 pub mod comments;
-
-use crate::comments::strip_comments_for_ext;
+pub mod compact;
+pub mod docstrings;
+pub mod lang;
+pub mod meta;
+pub mod output;
+pub mod redact;
+pub mod secrets;
+pub mod sensitive;
+pub mod skeleton;
+pub mod split;
+pub mod stats;
+pub mod toc;
+pub mod tokens;
+pub mod tree;
+pub mod types;
+
+pub use crate::output::OutputFormat;
+
+use crate::binary::{BinaryPolicy, SNIFF_BYTES, sniff, substitute};
+use crate::budget::{Budget, Priority};
+use crate::comments::{KeepList, StripLevel, strip_comments};
+use crate::compact::compact;
+use crate::docstrings::strip_docstrings;
+use crate::lang::Registry;
+use crate::meta::FileMeta;
+use crate::redact::Redactions;
+use crate::stats::Stats;
+use crate::tokens::{Tokenizer, count_tokens};
 
 /// Configuration passed from the CLI layer (main.rs) into the core logic.
 #[derive(Debug)]
 pub struct Config {
     pub exts: HashSet<String>,
+    /// Include every text file whatever its type (no `--type`, or `-t all`).
+    pub all_text: bool,
+    /// What to do with matched files that look binary.
+    pub binary: BinaryPolicy,
+    /// Exact file names selected by `--type`, e.g. `Makefile`.
+    pub filenames: HashSet<String>,
     pub paths: Vec<PathBuf>,
     pub follow_symlinks: bool,
     pub no_gitignore: bool,
-    pub json: bool,
+    pub format: OutputFormat,
     pub excludes: Vec<String>,
+    /// When non-empty, only files matching one of these globs are printed.
+    pub includes: Vec<String>,
     pub max_bytes: Option<u64>,
-    pub strip_comments: bool,
+    /// Print files on the sensitive-file denylist (`.env`, keys, ...).
+    pub allow_sensitive: bool,
+    /// Comment stripping level, or `None` to print files unchanged.
+    pub strip_comments: Option<StripLevel>,
+    /// Remove Python module, class and function docstrings.
+    pub strip_docstrings: bool,
+    /// Replace function bodies with `{ ... }` / `...` for supported languages.
+    pub skeleton: bool,
+    /// Trim trailing whitespace, collapse blank lines and shrink indentation.
+    pub compact: bool,
+    /// Replace likely secrets with `[REDACTED:kind]` before printing.
+    pub redact_secrets: bool,
+    /// Fail without printing anything if any likely secret is found.
+    pub fail_on_secret: bool,
+    /// User regex rules from `--redact` / `--redact-rules`.
+    pub redactions: Redactions,
+    /// Extra regexes for comments to keep when stripping.
+    pub keep_comments: Vec<String>,
+    /// Don't keep the built-in comments (doc comments, shebangs, pragmas).
+    pub no_default_keep: bool,
+    /// Known languages: comment syntax for stripping, names for Markdown
+    /// fences and `--meta`.
+    pub languages: Registry,
     pub end_marker: bool,
+    /// Emit a trailing `{"summary": {...}}` record in JSON Lines mode.
+    pub summary: bool,
+    /// Attach size, line count, language, hash, mtime and root to JSON entries.
+    pub meta: bool,
+    /// Print a per-file and total token summary to stderr.
+    pub stats: bool,
+    pub tokenizer: Tokenizer,
+    /// Stop including files once their combined size would exceed this budget.
+    pub budget: Option<Budget>,
+    /// Order in which files are considered for inclusion under `budget`.
+    pub priority: Priority,
+    /// Globs whose matches are considered first under `budget`, in order.
+    pub prefer: Vec<String>,
+    /// Write the output as `part-NNN` files, each under this size.
+    pub split: Option<Budget>,
+    /// Directory for `--split` part files. The CLI requires `--out-dir`
+    /// whenever `--split` is given, so this is always set with `split`.
+    pub out_dir: Option<PathBuf>,
+    /// Break files too large for a single part across several parts.
+    pub split_files: bool,
+    /// Print a directory tree of the emitted files before their contents.
+    pub tree: bool,
+    /// Print a table of contents with each file's starting line.
+    pub toc: bool,
+    /// Prefix content lines with their line number in the original file.
+    pub line_numbers: bool,
+}
+
+/// A matched file that has been read and transformed, ready to be emitted.
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    pub(crate) path: PathBuf,
+    pub(crate) display_path: String,
+    /// The root PATH (as given on the command line) this file was found under.
+    pub(crate) root: String,
+    /// Detected language name, for Markdown fences.
+    pub(crate) language: Option<String>,
+    /// Contents as they will be printed.
+    pub(crate) text: String,
+    pub(crate) tokens: Option<usize>,
+    /// Original line number of each line in `text`, for JSON `--line-numbers`.
+    pub(crate) line_numbers: Option<Vec<usize>>,
+    /// `(i, n)` when this is piece `i` of a file split across `n` parts.
+    pub(crate) chunk: Option<(usize, usize)>,
+    /// The detected format when the file is binary and `text` stands in
+    /// for it.
+    pub(crate) binary: Option<&'static str>,
+    /// File metadata for JSON entries, with `--meta`.
+    pub(crate) meta: Option<FileMeta>,
+}
+
+impl Document {
+    /// The path as shown in headers, marking split pieces.
+    pub(crate) fn label(&self) -> String {
+        match self.chunk {
+            Some((i, n)) => format!("{} (chunk {i} of {n})", self.display_path),
+            None => self.display_path.clone(),
+        }
+    }
 }
 
-#[derive(serde::Serialize)]
-struct FileEntry {
-    path: String,
-    file_name: String,
-    content: String,
+/// Counts gathered while walking, for reports and the exit status.
+#[derive(Debug, Default)]
+struct Tally {
+    /// Roots or files that could not be read.
+    errors: usize,
+    /// Likely secrets found by `--redact-secrets` / `--fail-on-secret`.
+    secrets: usize,
+    /// Binary files skipped.
+    binary: usize,
 }
 
 pub fn run_with_config(cfg: Config) -> Result<()> {
-    let exclude_globset = build_exclude_globset(&cfg.excludes)?;
+    let mut tally = Tally::default();
+    let mut stats = Stats::default();
+
+    let written = if needs_all_documents(&cfg) {
+        let mut docs = Vec::new();
+        walk_documents(&cfg, &mut tally, &mut |doc| {
+            docs.push(doc);
+            Ok(())
+        })?;
+        write_collected(&cfg, docs, &tally, &mut stats)
+    } else {
+        write_streaming(&cfg, &mut tally, &mut stats)
+    };
+    match written {
+        // The reader went away (e.g. `pc ... | head`); that's not a failure.
+        Err(err) if is_broken_pipe(&err) => {}
+        other => other?,
+    }
+
+    if cfg.redact_secrets {
+        eprintln!("Redacted {} likely secret(s).", tally.secrets);
+    }
+
+    if cfg.stats {
+        stats.skipped_binary = tally.binary;
+        stats.report(cfg.tokenizer);
+    }
+
+    if tally.errors > 0 {
+        anyhow::bail!("One or more files could not be read. See stderr for details.");
+    }
+
+    Ok(())
+}
+
+/// Whether the output depends on the full set of files, so nothing can be
+/// printed until the walk ends: budgets, split parts, the tree and table of
+/// contents, and `--fail-on-secret`.
+fn needs_all_documents(cfg: &Config) -> bool {
+    cfg.budget.is_some() || cfg.split.is_some() || cfg.toc || cfg.tree || cfg.fail_on_secret
+}
+
+/// Print each file as soon as it has been read, so the output (JSON Lines in
+/// particular) can be consumed while the walk is still running.
+fn write_streaming(cfg: &Config, tally: &mut Tally, stats: &mut Stats) -> Result<()> {
+    let mut out = io::stdout().lock();
+    let mut files = 0;
+    let mut bytes = 0;
+
+    out.write_all(cfg.format.prologue().as_bytes())?;
+    walk_documents(cfg, tally, &mut |doc| {
+        if files > 0 {
+            out.write_all(cfg.format.separator().as_bytes())?;
+        }
+        files += 1;
+        out.write_all(output::render_document(cfg, &doc, files)?.as_bytes())?;
+
+        bytes += doc.text.len() as u64;
+        if let Some(t) = doc.tokens {
+            stats.push(&doc.display_path, doc.text.len() as u64, t);
+        }
+        Ok(())
+    })?;
+    out.write_all(cfg.format.epilogue().as_bytes())?;
+
+    if cfg.format == OutputFormat::Jsonl && cfg.summary {
+        let tokens = wants_tokens(cfg).then(|| stats.total_tokens());
+        out.write_all(output::render_summary(files, bytes, tokens, tally.errors)?.as_bytes())?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// Select, arrange and print the files once all of them have been read.
+fn write_collected(
+    cfg: &Config,
+    mut docs: Vec<Document>,
+    tally: &Tally,
+    stats: &mut Stats,
+) -> Result<()> {
+    if cfg.fail_on_secret && tally.secrets > 0 {
+        anyhow::bail!(
+            "Found {} likely secret(s); nothing was printed (--fail-on-secret).",
+            tally.secrets
+        );
+    }
+
+    if let Some(budget) = cfg.budget {
+        let prefer = build_globset(&cfg.prefer, "--prefer")?;
+        let dropped;
+        (docs, dropped) = budget::select(docs, budget, cfg.priority, prefer.as_ref());
+        budget::report_dropped(&dropped, budget);
+    }
 
-    let mut had_error = false;
-    let mut first_file = true;
+    for doc in &docs {
+        if let Some(t) = doc.tokens {
+            stats.push(&doc.display_path, doc.text.len() as u64, t);
+        }
+    }
+
+    let summary = if cfg.format == OutputFormat::Jsonl && cfg.summary {
+        let tokens = wants_tokens(cfg).then(|| stats.total_tokens());
+        let bytes = docs.iter().map(|d| d.text.len() as u64).sum();
+        Some(output::render_summary(
+            docs.len(),
+            bytes,
+            tokens,
+            tally.errors,
+        )?)
+    } else {
+        None
+    };
 
-    if cfg.json {
-        println!("[");
+    // Sections printed ahead of the files themselves.
+    let mut preamble = Vec::new();
+    if cfg.tree {
+        preamble.push(tree::render(cfg.format, &docs)?);
     }
 
+    match cfg.split {
+        Some(limit) => {
+            let out_dir = cfg.out_dir.as_deref().unwrap_or(Path::new("."));
+            split::write_parts(cfg, docs, limit, out_dir, &preamble, summary.as_deref())?;
+            Ok(())
+        }
+        None => {
+            let sections = docs
+                .iter()
+                .enumerate()
+                .map(|(index, doc)| output::render_document(cfg, doc, index + 1))
+                .collect::<Result<Vec<_>>>()?;
+
+            if cfg.toc {
+                let toc = toc::render(cfg.format, &docs, &preamble, &sections)?;
+                preamble.push(toc);
+            }
+
+            write_stdout(cfg, &preamble, &sections, summary.as_deref())
+        }
+    }
+}
+
+/// Write the preamble and rendered file sections to stdout as a single output
+/// in the configured format.
+fn write_stdout(
+    cfg: &Config,
+    preamble: &[String],
+    sections: &[String],
+    summary: Option<&str>,
+) -> Result<()> {
+    let mut out = io::stdout().lock();
+
+    out.write_all(cfg.format.prologue().as_bytes())?;
+    for (index, section) in preamble.iter().chain(sections).enumerate() {
+        if index > 0 {
+            out.write_all(cfg.format.separator().as_bytes())?;
+        }
+        out.write_all(section.as_bytes())?;
+    }
+
+    out.write_all(cfg.format.epilogue().as_bytes())?;
+
+    if let Some(summary) = summary {
+        out.write_all(summary.as_bytes())?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Token counts are only computed when something will report or use them, so
+/// JSON entries only carry `tokens` with `--stats`, `--meta` or a token
+/// budget.
+fn wants_tokens(cfg: &Config) -> bool {
+    cfg.stats || cfg.meta || matches!(cfg.budget, Some(Budget::Tokens(_)))
+}
+
+/// Walk every root, apply the ignore / exclude / extension / size filters, and
+/// read the matching files, passing each to `emit` in walk order. Read errors,
+/// likely secrets and skipped binaries are counted in `tally`. An error from
+/// `emit` stops the walk.
+fn walk_documents(
+    cfg: &Config,
+    tally: &mut Tally,
+    emit: &mut dyn FnMut(Document) -> Result<()>,
+) -> Result<()> {
+    let exclude_globset = build_exclude_globset(&cfg.excludes)?;
+    let include_globset = build_globset(&cfg.includes, "--include")?;
+    let keep = KeepList::new(&cfg.keep_comments, !cfg.no_default_keep)?;
+    let count = wants_tokens(cfg);
+
     for raw_root in &cfg.paths {
         // Canonicalise roots so running from arbitrary working dirs is reliable.
         let canon_root = match raw_root.canonicalize() {
             Ok(p) => p,
             Err(e) => {
                 eprintln!("Skipping root {:?}: {}", raw_root, e);
-                had_error = true;
+                tally.errors += 1;
                 continue;
             }
         };
@@ -76,6 +387,7 @@ pub fn run_with_config(cfg: Config) -> Result<()> {
         // Values moved into the 'static filter closure must be owned separately.
         let root_for_filter = canon_root.clone();
         let exclude_globset = exclude_globset.clone();
+        let include_globset = include_globset.clone();
 
         builder.filter_entry(move |entry: &DirEntry| {
             // Always keep the root.
@@ -88,21 +400,21 @@ pub fn run_with_config(cfg: Config) -> Result<()> {
                 return true;
             }
 
-            // Apply user exclude globs, relative to the current root.
-            if let Some(ref gs) = exclude_globset {
-                let path = entry.path();
-                let rel = path.strip_prefix(&root_for_filter).unwrap_or(path);
-                let rel_norm = normalize_for_matching(rel);
+            let path = entry.path();
+            let rel = path.strip_prefix(&root_for_filter).unwrap_or(path);
+            let rel_norm = normalize_for_matching(rel);
+            let is_dir = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
 
+            // Apply user exclude globs, relative to the current root. Excludes
+            // win over includes.
+            if let Some(ref gs) = exclude_globset {
                 if gs.is_match(&rel_norm) {
                     return false;
                 }
 
                 // If this is a directory, also try a trailing slash to make patterns
                 // like `tests/**` able to prune the whole subtree early.
-                if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
-                    && !rel_norm.ends_with('/')
-                {
+                if is_dir && !rel_norm.ends_with('/') {
                     let rel_dir = format!("{rel_norm}/");
                     if gs.is_match(&rel_dir) {
                         return false;
@@ -110,6 +422,14 @@ pub fn run_with_config(cfg: Config) -> Result<()> {
                 }
             }
 
+            // Apply user include globs to files only: a directory may hold
+            // matching files even when its own path matches nothing.
+            if let Some(ref gs) = include_globset
+                && !is_dir
+            {
+                return gs.is_match(&rel_norm);
+            }
+
             true
         });
 
@@ -120,7 +440,7 @@ pub fn run_with_config(cfg: Config) -> Result<()> {
                 Ok(e) => e,
                 Err(err) => {
                     eprintln!("Walk error: {err}");
-                    had_error = true;
+                    tally.errors += 1;
                     continue;
                 }
             };
@@ -130,12 +450,23 @@ pub fn run_with_config(cfg: Config) -> Result<()> {
             }
 
             let path = entry.path();
-            if !matches_ext(path, &cfg.exts) {
+            if !cfg.all_text
+                && !matches_ext(path, &cfg.exts)
+                && !matches_filename(path, &cfg.filenames)
+                && !matches_language(cfg, path)
+            {
                 continue;
             }
 
             let display_path = make_display_path(&canon_root, path);
 
+            if !cfg.allow_sensitive && sensitive::is_sensitive(path) {
+                eprintln!(
+                    "Skipping {display_path} (sensitive file; use --allow-sensitive to include)"
+                );
+                continue;
+            }
+
             if let Some(limit) = cfg.max_bytes
                 && let Ok(meta) = fs::metadata(path)
                 && meta.len() > limit
@@ -149,38 +480,78 @@ pub fn run_with_config(cfg: Config) -> Result<()> {
                 continue;
             }
 
-            if cfg.json {
-                if !first_file {
-                    println!(",");
+            let binary = read_head(path, SNIFF_BYTES as u64).and_then(|head| sniff(&head));
+            if let Some(kind) = binary
+                && cfg.binary == BinaryPolicy::Skip
+            {
+                // Without a type filter binaries were never asked for, so
+                // only `--stats` counts them.
+                if !cfg.all_text {
+                    eprintln!("Skipping {display_path} (binary: {kind})");
                 }
-                if let Err(err) = print_file_json(path, &display_path, cfg.strip_comments) {
+                tally.binary += 1;
+                continue;
+            }
+
+            let read = fs::read(path).with_context(|| format!("Failed to read {}", display_path));
+            let bytes = match read {
+                Ok(b) => b,
+                Err(err) => {
                     eprintln!("Error printing {}: {:#}", display_path, err);
-                    had_error = true;
+                    tally.errors += 1;
+                    continue;
                 }
-                first_file = false;
-            } else if let Err(err) =
-                print_file(path, &display_path, cfg.end_marker, cfg.strip_comments)
-            {
-                eprintln!("Error printing {}: {:#}", display_path, err);
-                had_error = true;
-            }
+            };
+            let (text, line_numbers, stripped) = match binary {
+                Some(kind) => (substitute(cfg.binary, kind, &bytes), None, false),
+                None => {
+                    let mut redacted = scan_secrets(cfg, &display_path, &bytes, &mut tally.secrets);
+                    if !cfg.redactions.is_empty() {
+                        let text = redacted
+                            .as_deref()
+                            .map_or_else(|| String::from_utf8_lossy(&bytes), Cow::Borrowed);
+                        if let Some(text) = cfg.redactions.apply(&display_path, &text) {
+                            redacted = Some(text);
+                        }
+                    }
+                    let source = redacted.as_ref().map_or(&bytes[..], |text| text.as_bytes());
+                    decode_text(cfg, &keep, path, source)
+                }
+            };
+            let tokens = count.then(|| count_tokens(&text, cfg.tokenizer));
+            let language = cfg.languages.detect(path, &bytes);
+            let root = normalize_for_matching(raw_root);
+            let meta = cfg
+                .meta
+                .then(|| FileMeta::collect(path, &bytes, language, &root, stripped));
+
+            emit(Document {
+                path: path.to_path_buf(),
+                display_path,
+                root,
+                language: language.map(|lang| lang.name.clone()),
+                text,
+                tokens,
+                line_numbers,
+                chunk: None,
+                binary,
+                meta,
+            })?;
         }
     }
 
-    if cfg.json {
-        println!("\n]");
-    }
-
-    if had_error {
-        anyhow::bail!("One or more files could not be read. See stderr for details.");
-    }
-
     Ok(())
 }
 
 /// Build a GlobSet from the user–provided `--exclude` patterns.
 /// Returns `Ok(None)` if there are no patterns.
 fn build_exclude_globset(patterns: &[String]) -> Result<Option<GlobSet>> {
+    build_globset(patterns, "--exclude")
+}
+
+/// Build a GlobSet from user-provided patterns, naming `flag` in errors.
+/// Returns `Ok(None)` if there are no patterns.
+fn build_globset(patterns: &[String], flag: &str) -> Result<Option<GlobSet>> {
     if patterns.is_empty() {
         return Ok(None);
     }
@@ -193,14 +564,13 @@ fn build_exclude_globset(patterns: &[String]) -> Result<Option<GlobSet>> {
             continue;
         }
 
-        let glob =
-            Glob::new(pat).with_context(|| format!("Invalid --exclude glob pattern: {pat}"))?;
+        let glob = Glob::new(pat).with_context(|| format!("Invalid {flag} glob pattern: {pat}"))?;
         builder.add(glob);
     }
 
     let set = builder
         .build()
-        .context("Failed to build exclude glob set")?;
+        .with_context(|| format!("Failed to build {flag} glob set"))?;
 
     Ok(Some(set))
 }
@@ -213,6 +583,42 @@ pub fn matches_ext(path: &Path, exts: &HashSet<String>) -> bool {
     }
 }
 
+/// Exact file name match, using the provided file name set.
+pub fn matches_filename(path: &Path, filenames: &HashSet<String>) -> bool {
+    path.file_name()
+        .and_then(|s| s.to_str())
+        .is_some_and(|name| filenames.contains(name))
+}
+
+/// Whether `path`, by its file name (`Makefile`) or, when it has no
+/// extension, its shebang line, is in a language with a selected extension.
+fn matches_language(cfg: &Config, path: &Path) -> bool {
+    let language = match cfg.languages.for_filename(path) {
+        Some(lang) => Some(lang),
+        None if path.extension().is_none() => {
+            read_head(path, 256).and_then(|head| cfg.languages.for_shebang(&head))
+        }
+        None => None,
+    };
+
+    language.is_some_and(|lang| {
+        lang.extensions
+            .iter()
+            .any(|ext| cfg.exts.contains(&ext.to_ascii_lowercase()))
+    })
+}
+
+/// Up to the first `len` bytes of `path`.
+fn read_head(path: &Path, len: u64) -> Option<Vec<u8>> {
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(len)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(head)
+}
+
 /// Produce a display path relative to `root` (stable regardless of current working directory).
 pub fn make_display_path(root: &Path, path: &Path) -> String {
     let rel = path.strip_prefix(root).unwrap_or(path);
@@ -228,63 +634,160 @@ pub fn make_display_path(root: &Path, path: &Path) -> String {
     normalize_for_matching(rel)
 }
 
-/// Print a single file with header (and optional end marker), optionally stripping comments.
-pub fn print_file(
-    path: &Path,
+/// Report likely secrets in `bytes` on stderr, adding them to `found`.
+/// Returns the redacted text when `--redact-secrets` is on and something was
+/// found.
+fn scan_secrets(
+    cfg: &Config,
     display_path: &str,
-    end_marker: bool,
-    strip_comments: bool,
-) -> Result<()> {
-    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", display_path))?;
-    let contents_lossy = String::from_utf8_lossy(&bytes);
-    let mut text = contents_lossy.into_owned();
+    bytes: &[u8],
+    found: &mut usize,
+) -> Option<String> {
+    if !cfg.redact_secrets && !cfg.fail_on_secret {
+        return None;
+    }
 
-    if strip_comments {
-        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-        text = strip_comments_for_ext(&text, ext);
+    let (redacted, secrets) = secrets::redact(&String::from_utf8_lossy(bytes));
+    let verb = if cfg.redact_secrets {
+        "Redacted"
+    } else {
+        "Found"
+    };
+    for secret in &secrets {
+        eprintln!("{verb} {} at {display_path}:{}", secret.kind, secret.line);
     }
+    *found += secrets.len();
 
-    println!("========== FILE: {} ==========", display_path);
-    print!("{text}");
+    (cfg.redact_secrets && !secrets.is_empty()).then_some(redacted)
+}
 
-    // Ensure there is a trailing newline before the separator between files.
-    if !text.ends_with('\n') {
-        println!();
+/// Decode file contents as (lossy) UTF-8 text, optionally reducing it to a
+/// skeleton, stripping comments and docstrings, and compacting whitespace.
+///
+/// With `--line-numbers`, text formats get each line prefixed with its line
+/// number in the original file, while JSON formats keep the content as-is and
+/// return the numbers separately. The flag reports whether any of the
+/// transforms changed a line (line numbers don't count).
+fn decode_text(
+    cfg: &Config,
+    keep: &KeepList,
+    path: &Path,
+    bytes: &[u8],
+) -> (String, Option<Vec<usize>>, bool) {
+    let contents_lossy = String::from_utf8_lossy(bytes);
+
+    let language = cfg.languages.detect(path, bytes);
+    let docstrings =
+        cfg.strip_docstrings && language.is_some_and(|lang| docstrings::applies_to(&lang.name));
+    let outline = language
+        .map(|lang| lang.name.as_str())
+        .filter(|name| cfg.skeleton && skeleton::supports(name));
+
+    if cfg.strip_comments.is_none()
+        && !docstrings
+        && outline.is_none()
+        && !cfg.compact
+        && !cfg.line_numbers
+    {
+        return (contents_lossy.into_owned(), None, false);
     }
 
-    if end_marker {
-        println!("========== END FILE: {} ==========\n", display_path);
+    // Docstring stripping keeps every newline, so line numbers still line up.
+    let source: Cow<str> = if docstrings {
+        Cow::Owned(strip_docstrings(&contents_lossy))
     } else {
-        println!();
-    }
+        Cow::Borrowed(&contents_lossy)
+    };
 
-    Ok(())
-}
+    // Each step keeps lines paired with their number in the file on disk.
+    let lines: Vec<(usize, String)> =
+        match outline.and_then(|name| skeleton::outline(&source, name)) {
+            Some(outline) => outline,
+            None => source
+                .lines()
+                .enumerate()
+                .map(|(idx, line)| (idx + 1, line.to_string()))
+                .collect(),
+        };
 
-fn print_file_json(path: &Path, display_path: &str, strip_comments: bool) -> Result<()> {
-    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", display_path))?;
-    let contents_lossy = String::from_utf8_lossy(&bytes);
-    let mut text = contents_lossy.into_owned();
+    let lines: Vec<(usize, String)> = match cfg.strip_comments {
+        Some(level) => {
+            let syntax = language.and_then(|lang| lang.comments.as_ref());
+            let mut kept = Vec::new();
+            let mut prev = 0;
+            for (n, line) in strip_comments(&join_lines(&lines), syntax, level, keep) {
+                // `--compact` keeps one blank line where the source had any.
+                if cfg.compact
+                    && let Some((blank, _)) =
+                        lines[prev..n - 1].iter().find(|(_, l)| l.trim().is_empty())
+                {
+                    kept.push((*blank, String::new()));
+                }
+                kept.push((lines[n - 1].0, line));
+                prev = n;
+            }
+            kept
+        }
+        // Drop lines emptied by docstring removal, but keep blank lines.
+        None => {
+            let original: Vec<&str> = contents_lossy.lines().collect();
+            lines
+                .into_iter()
+                .filter(|(n, line)| {
+                    !line.trim().is_empty()
+                        || original.get(n - 1).is_none_or(|o| o.trim().is_empty())
+                })
+                .collect()
+        }
+    };
+
+    let lines = if cfg.compact {
+        compact(lines, language)
+    } else {
+        lines
+    };
 
-    if strip_comments {
-        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-        text = strip_comments_for_ext(&text, ext);
+    let stripped = lines
+        .iter()
+        .map(|(_, line)| line.as_str())
+        .ne(contents_lossy.lines());
+
+    if !cfg.line_numbers {
+        return (join_lines(&lines), None, stripped);
     }
 
-    let entry = FileEntry {
-        path: display_path.to_string(),
-        file_name: path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string(),
-        content: text,
-    };
+    match cfg.format {
+        OutputFormat::Json | OutputFormat::Jsonl => (
+            join_lines(&lines),
+            Some(lines.iter().map(|(n, _)| *n).collect()),
+            stripped,
+        ),
+        OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Xml => {
+            (number_lines(&lines), None, stripped)
+        }
+    }
+}
 
-    let json = serde_json::to_string(&entry)?;
-    print!("{}", json);
+/// Join numbered lines back into text, one `\n` after each.
+fn join_lines(lines: &[(usize, String)]) -> String {
+    let mut out = String::new();
+    for (_, line) in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
 
-    Ok(())
+/// Prefix each line with its right-aligned line number, e.g. `  42 | code`.
+fn number_lines(lines: &[(usize, String)]) -> String {
+    let last = lines.last().map(|(n, _)| *n).unwrap_or(0);
+    let width = last.to_string().len().max(4);
+
+    let mut out = String::new();
+    for (n, line) in lines {
+        out.push_str(&format!("{n:>width$} | {line}\n"));
+    }
+    out
 }
 
 /// Convert paths to a stable, slash-separated form for matching/printing.
@@ -308,4 +811,20 @@ mod tests {
         assert!(!matches_ext(Path::new("README"), &exts));
         assert!(!matches_ext(Path::new("script.sh"), &exts));
     }
+
+    #[test]
+    fn matches_filename_is_exact() {
+        let names = HashSet::from(["Makefile".to_string()]);
+
+        assert!(matches_filename(Path::new("sub/Makefile"), &names));
+        assert!(!matches_filename(Path::new("makefile"), &names));
+        assert!(!matches_filename(Path::new("Makefile.am"), &names));
+    }
+
+    #[test]
+    fn number_lines_pads_to_widest_number() {
+        let lines = vec![(1, "a".to_string()), (3, "b".to_string())];
+        assert_eq!(number_lines(&lines), "   1 | a\n   3 | b\n");
+        assert_eq!(number_lines(&[(12345, "x".to_string())]), "12345 | x\n");
+    }
 }
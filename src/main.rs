@@ -1,9 +1,15 @@
-use std::collections::HashSet;
 use std::path::PathBuf;
 
 use anyhow::{Result, bail};
 use clap::{ArgAction, Parser};
-use pc::{Config, run_with_config};
+use pc::binary::BinaryPolicy;
+use pc::budget::{Budget, Priority};
+use pc::comments::StripLevel;
+use pc::lang::Registry;
+use pc::redact::Redactions;
+use pc::tokens::Tokenizer;
+use pc::types::Types;
+use pc::{Config, OutputFormat, run_with_config};
 
 /// pc - print code.
 ///
@@ -32,26 +38,47 @@ By default it:
 Typical usage:
   pc -t py
   pc -t py,rs src tests
+  pc            (every text file)
 "#
 )]
 struct Args {
-    /// File extensions / types to include (e.g. py, rs).
+    /// File types or extensions to include (e.g. rust, web, py, rs).
     ///
+    /// Omit it, or pass `-t all`, to include every text file; files that
+    /// look binary (NUL bytes, invalid UTF-8, known magic numbers) are
+    /// skipped and counted in --stats.
+    ///
+    /// A type name (see --type-list) selects all of its extensions and file
+    /// names, so `-t cpp` covers .cpp, .hpp, .cc, .cxx, ...; anything else is
+    /// a plain extension. A type named like an extension always includes it
+    /// (`-t c` is .c and .h). A leading dot forces a plain extension (`-t .c`).
     /// Can be repeated or comma-separated:
     ///   pc -t py
     ///   pc -t py,rs
-    ///   pc -t py -t rs
+    ///   pc -t c,cpp -t make
     #[arg(
         short = 't',
         long = "type",
         alias = "ext",
-        value_name = "EXT",
+        value_name = "TYPE",
         action = ArgAction::Append,
-        value_delimiter = ',',
-        required = true
+        value_delimiter = ','
     )]
     exts: Vec<String>,
 
+    /// Print every known type and the files it matches, then exit.
+    #[arg(long = "type-list")]
+    type_list: bool,
+
+    /// Define a type, or add files to one (repeatable).
+    ///
+    /// Either NAME:GLOB[,GLOB...], where each GLOB is `*.EXT` or an exact
+    /// file name, or NAME:include:TYPE[,TYPE...] to combine types:
+    ///   pc --type-add 'tmpl:*.tmpl,*.j2' -t tmpl
+    ///   pc --type-add 'app:include:rust,web' -t app
+    #[arg(long = "type-add", value_name = "SPEC", action = ArgAction::Append)]
+    type_add: Vec<String>,
+
     /// Paths to scan (files or directories). Defaults to current directory.
     ///
     /// You can pass multiple:
@@ -72,6 +99,26 @@ struct Args {
     #[arg(long = "no-gitignore")]
     no_gitignore: bool,
 
+    /// Print files that look like credentials, which are skipped by default.
+    ///
+    /// The built-in denylist covers .env / .env.*, SSH and TLS keys (id_rsa,
+    /// *.pem, *.key, *.p12, ...), credentials.json, .npmrc, .netrc, *.kdbx,
+    /// Terraform state and similar. Skipped files are reported on stderr.
+    #[arg(long = "allow-sensitive")]
+    allow_sensitive: bool,
+
+    /// What to do with matched files that look binary.
+    ///
+    /// A file is binary when it starts with a known magic number (gzip, zip,
+    /// PNG, PDF, ELF, ...) or its first 8 KiB contain a NUL byte or mostly
+    /// invalid UTF-8:
+    ///
+    ///   skip         leave it out and say so on stderr (default)
+    ///   placeholder  print the header with "[binary file: gzip, 1234 bytes]"
+    ///   base64       print the contents base64-encoded (--json / --jsonl only)
+    #[arg(long = "binary", value_name = "POLICY", value_enum, default_value_t)]
+    binary: BinaryPolicy,
+
     /// Additional glob patterns to exclude (files or directories).
     ///
     /// Patterns are evaluated relative to each PATH root and use glob-style
@@ -90,25 +137,274 @@ struct Args {
     )]
     excludes: Vec<String>,
 
+    /// Only print files matching at least one of these glob patterns.
+    ///
+    /// Patterns are evaluated relative to each PATH root, like --exclude, so
+    /// display paths keep their usual prefix:
+    ///
+    ///   pc -t py --include 'src/api/**,src/models/**'
+    ///
+    /// Directories are always descended into. A path matching both an
+    /// --include and an --exclude pattern is excluded.
+    #[arg(
+        long = "include",
+        short = 'I',
+        value_name = "GLOB",
+        action = ArgAction::Append,
+        value_delimiter = ','
+    )]
+    includes: Vec<String>,
+
     /// Maximum file size to print, in bytes (skip larger files).
     ///
     /// Useful when you want to avoid dumping big generated artifacts.
     #[arg(long = "max-bytes", value_name = "N")]
     max_bytes: Option<u64>,
 
-    /// Strip full-line comments and blank lines when printing.
+    /// Strip comments and blank lines when printing.
+    ///
+    /// LEVEL is one of:
+    ///   lines  - (default) drop lines whose first non-whitespace chars are a
+    ///            comment marker, for known extensions (py, sh, rs, c, cpp,
+    ///            js, ts, java, go, sql, etc.)
+    ///   blocks - also remove block comments (/* */, <!-- -->, --[[ ]], ...)
+    ///            using a lexer that skips string literals
+    ///   aggressive - also remove trailing comments after code
+    ///            (`x = 1  # note`, `f(); // note`)
+    ///
+    /// Examples:
+    ///   pc -t rs --strip-comments
+    ///   pc -t rs --strip-comments=blocks
+    ///   pc -t py --strip-comments=aggressive
+    #[arg(
+        long = "strip-comments",
+        value_name = "LEVEL",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "lines"
+    )]
+    strip_comments: Option<StripLevel>,
+
+    /// Remove Python module, class and function docstrings (.py, .pyi).
+    ///
+    /// Only string literals that are the first statement of a module, class
+    /// or function are removed; assigned or passed strings are kept. Combines
+    /// with --strip-comments:
+    ///   pc -t py --strip-docstrings --strip-comments
+    #[arg(long = "strip-docstrings")]
+    strip_docstrings: bool,
+
+    /// Print only the outline of each file: imports, types and signatures,
+    /// with function bodies replaced by `{ ... }` (or `...` in Python).
+    ///
+    /// Supported for Rust, Python, Go, Java, C, C++, JavaScript and
+    /// TypeScript; other files are printed in full. Combine with the usual
+    /// filters to outline a whole repo, then print a few files in full:
+    ///   pc -t rs,py --skeleton .
+    #[arg(long = "skeleton")]
+    skeleton: bool,
+
+    /// Compact whitespace: trim trailing spaces, collapse runs of blank lines
+    /// to one and shrink indentation to one space per level.
+    ///
+    /// Indentation is kept as-is for languages where it matters (Python,
+    /// YAML, Makefiles, ...) and for unknown file types, and multi-line
+    /// strings are never touched. With --strip-comments, a single blank line
+    /// is kept where the source had blank lines:
+    ///   pc -t py --strip-comments --compact
+    #[arg(long = "compact")]
+    compact: bool,
+
+    /// Replace likely secrets with `[REDACTED:kind]` before printing.
+    ///
+    /// Detects AWS access keys, private key blocks, GitHub and Slack tokens,
+    /// JWTs, and high-entropy values assigned to names like `password`,
+    /// `secret`, `api_key` or `token`. Each redaction is reported on stderr
+    /// as `Redacted KIND at PATH:LINE`.
+    #[arg(long = "redact-secrets")]
+    redact_secrets: bool,
+
+    /// Exit with an error, printing nothing, if any likely secret is found.
+    ///
+    /// Uses the same detectors as --redact-secrets and reports each finding
+    /// on stderr, e.g. as a CI guard:
+    ///   pc -t py,yaml,toml --fail-on-secret . > /dev/null
+    #[arg(long = "fail-on-secret")]
+    fail_on_secret: bool,
+
+    /// Replace matches of REGEX in every file (repeatable).
     ///
-    /// For known extensions (py, sh, rs, c, cpp, js, ts, java, go, sql, etc.)
-    /// this drops lines whose first non-whitespace chars are a comment marker.
-    /// It deliberately does NOT try to strip inline/trailing comments or block
-    /// comments to avoid breaking code.
-    #[arg(long = "strip-comments")]
-    strip_comments: bool,
+    /// The replacement defaults to `[REDACTED]` and may use capture groups
+    /// as `$1` or `${name}`. Write `\=` for a literal `=` in REGEX:
+    ///   pc -t py --redact '[a-z0-9-]+\.corp\.internal=host.example'
+    ///   pc -t py --redact '(?P<user>[\w.]+)@acme\.com=${user}@example.com'
+    #[arg(long = "redact", value_name = "REGEX[=REPLACEMENT]", action = ArgAction::Append)]
+    redact: Vec<String>,
+
+    /// Read more redaction rules from a TOML file, applied after --redact.
+    ///
+    /// Each `[[redact]]` entry has a `pattern`, an optional `replacement` and
+    /// optional `paths` globs limiting it to some files:
+    ///   [[redact]]
+    ///   pattern = 'CUST-\d{6}'
+    ///   replacement = "CUST-XXXXXX"
+    ///   paths = ["fixtures/**"]
+    #[arg(long = "redact-rules", value_name = "FILE")]
+    redact_rules: Option<PathBuf>,
+
+    /// Keep comments matching REGEX when stripping (repeatable).
+    ///
+    /// Matched against the comment text, starting at its marker. Adds to the
+    /// built-in keep-list of doc comments (///, //!), shebangs, coding lines,
+    /// SAFETY notes and type-checker / formatter pragmas:
+    ///   pc -t rs --strip-comments --keep-comment '^// TODO'
+    #[arg(long = "keep-comment", value_name = "REGEX", action = ArgAction::Append)]
+    keep_comments: Vec<String>,
+
+    /// Strip the built-in keep-list comments too; only --keep-comment
+    /// patterns are kept.
+    #[arg(long = "no-default-keep")]
+    no_default_keep: bool,
+
+    /// TOML file with extra or overriding language definitions.
+    ///
+    /// Uses the same format as the built-in table: per language a name,
+    /// extensions, file names, and comment / string syntax:
+    ///   pc -t nim --strip-comments --languages ~/.config/pc/languages.toml
+    #[arg(long = "languages", value_name = "FILE")]
+    languages: Option<PathBuf>,
 
     /// Output as a JSON array of objects { "path": "...", "content": "..." }.
-    #[arg(long = "json")]
+    ///
+    /// Entries carry a `tokens` field only with --stats or --meta (or a token
+    /// --budget), so the tokenizer is not run otherwise. Shorthand for
+    /// `--format json`.
+    #[arg(long = "json", conflicts_with = "format")]
     json: bool,
 
+    /// Output as JSON Lines: one self-contained file object per line.
+    ///
+    /// Unlike `--json`, the output can be streamed, split and shuffled line by
+    /// line. Shorthand for `--format jsonl`.
+    #[arg(long = "jsonl", conflicts_with_all = ["format", "json"])]
+    jsonl: bool,
+
+    /// In JSON Lines mode, append a final `{"summary": {...}}` record with
+    /// file, byte and error totals.
+    #[arg(long = "summary")]
+    summary: bool,
+
+    /// Output format: `text` (default banners), `json`, `jsonl`, `markdown` or `xml`.
+    ///
+    /// Markdown emits a `### path` heading and a fenced code block per file,
+    /// tagged with a language derived from the extension (rs -> rust, etc.):
+    ///   pc -t rs --format markdown
+    ///
+    /// XML wraps each file in `<document index="N">` with `<source>` and
+    /// `<document_content>` children, inside a `<documents>` root.
+    #[arg(long = "format", value_name = "FORMAT", value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Attach file metadata to JSON / JSON Lines entries.
+    ///
+    /// Adds `size`, `lines`, `language`, `sha256` (of the file on disk),
+    /// `modified` (Unix seconds), `root`, `stripped` and `tokens` fields to
+    /// each object. `stripped` is true when --strip-comments,
+    /// --strip-docstrings, --skeleton or --compact changed the content.
+    #[arg(long = "meta")]
+    meta: bool,
+
+    /// Print a per-file and total token / byte summary to stderr.
+    ///
+    /// Also adds a `tokens` field to JSON / JSON Lines entries.
+    #[arg(long = "stats")]
+    stats: bool,
+
+    /// Tokenizer used for token counts: `o200k` (default), `cl100k`, or
+    /// `approx` (one token per four characters, fastest).
+    #[arg(long = "tokenizer", value_name = "NAME", value_enum, default_value_t)]
+    tokenizer: Tokenizer,
+
+    /// Stop including files once the total would exceed this budget.
+    ///
+    /// A plain number is a token count (`8000`, `128k`); a `B`, `KB` or `MB`
+    /// suffix makes it a byte count (`500KB`). Files that don't fit are
+    /// listed on stderr:
+    ///   pc -t rs --budget 100k --priority smallest
+    #[arg(long = "budget", value_name = "N")]
+    budget: Option<Budget>,
+
+    /// Order in which files are considered under `--budget`: `walk`
+    /// (default), `smallest`, `recent` (most recently modified) or `shallow`
+    /// (closest to the root).
+    #[arg(
+        long = "priority",
+        value_name = "ORDER",
+        value_enum,
+        default_value_t,
+        requires = "budget"
+    )]
+    priority: Priority,
+
+    /// Glob patterns considered first under `--budget`, in the order given.
+    ///
+    /// Matched against the printed path, e.g.:
+    ///   pc -t rs --budget 50k --prefer 'src/api/**' --prefer 'src/**'
+    #[arg(
+        long = "prefer",
+        value_name = "GLOB",
+        action = ArgAction::Append,
+        value_delimiter = ',',
+        requires = "budget"
+    )]
+    prefer: Vec<String>,
+
+    /// Write the output to `part-001.txt`, `part-002.txt`, ... in `--out-dir`,
+    /// each under SIZE.
+    ///
+    /// SIZE uses the same syntax as `--budget` (`8k` tokens, `200KB` bytes).
+    /// Each part starts with a `part i of n` header listing its files, and a
+    /// file's header is never separated from its content:
+    ///   pc -t rs --split 100k --out-dir /tmp/parts
+    #[arg(long = "split", value_name = "SIZE", requires = "out_dir")]
+    split: Option<Budget>,
+
+    /// Directory for `--split` part files (created if missing). Part files
+    /// from an earlier run in it are replaced.
+    #[arg(long = "out-dir", value_name = "DIR", requires = "split")]
+    out_dir: Option<PathBuf>,
+
+    /// With `--split`, break files too large for one part across several
+    /// parts on line boundaries, marking each piece `(chunk i of n)`.
+    #[arg(long = "split-files", requires = "split")]
+    split_files: bool,
+
+    /// Print a directory tree of the emitted files before their contents.
+    ///
+    /// The tree reflects exactly what pc prints (after .gitignore, --exclude,
+    /// extension and size filtering). JSON output gets a leading
+    /// `{"tree": {...}}` record instead.
+    #[arg(long = "tree")]
+    tree: bool,
+
+    /// Print a table of contents listing each file's size, line count and
+    /// the line where its section starts in the output.
+    ///
+    /// Handy for jumping around a large dump in an editor, or for asking a
+    /// model to reference a file by position.
+    #[arg(long = "toc", conflicts_with = "split")]
+    toc: bool,
+
+    /// Prefix each line with its line number in the original file, e.g.
+    /// `  42 | code`.
+    ///
+    /// Numbers are taken before --strip-comments removes lines, so they still
+    /// match the file on disk. JSON output keeps `content` unchanged and adds
+    /// a `line_numbers` array with the original number of each line instead.
+    #[arg(long = "line-numbers", short = 'n')]
+    line_numbers: bool,
+
     /// Print an explicit END marker after each file.
     ///
     /// This is handy if you want a clear end-of-file delimiter for tooling.
@@ -126,30 +422,98 @@ fn main() {
 fn run() -> Result<()> {
     let args = Args::parse();
 
-    // Normalise extensions to lowercase, no leading dot.
-    let mut ext_set = HashSet::new();
-    for e in &args.exts {
-        let norm = e.trim().trim_start_matches('.').to_ascii_lowercase();
-        if !norm.is_empty() {
-            ext_set.insert(norm);
-        }
+    let languages = Registry::load(args.languages.as_deref())?;
+
+    let mut types = Types::new(&languages)?;
+    for spec in &args.type_add {
+        types.add(spec)?;
+    }
+
+    if args.type_list {
+        print!("{}", types.list());
+        return Ok(());
     }
 
-    if ext_set.is_empty() {
+    // Expand type names; other values are extensions (lowercase, no dot).
+    let all_text = args.exts.is_empty()
+        || args
+            .exts
+            .iter()
+            .any(|value| value.trim().eq_ignore_ascii_case("all"));
+    let (ext_set, filenames) = types.select(&args.exts);
+
+    if !all_text && ext_set.is_empty() && filenames.is_empty() {
         bail!("No valid extensions provided (after normalisation).");
     }
 
+    let format = if args.json {
+        OutputFormat::Json
+    } else if args.jsonl {
+        OutputFormat::Jsonl
+    } else {
+        args.format.unwrap_or_default()
+    };
+
+    if args.summary && format != OutputFormat::Jsonl {
+        bail!("--summary is only supported with JSON Lines output (--jsonl).");
+    }
+
+    if args.meta && !matches!(format, OutputFormat::Json | OutputFormat::Jsonl) {
+        bail!("--meta is only supported with JSON output (--json or --jsonl).");
+    }
+
+    if args.binary == BinaryPolicy::Base64
+        && !matches!(format, OutputFormat::Json | OutputFormat::Jsonl)
+    {
+        bail!("--binary base64 is only supported with JSON output (--json or --jsonl).");
+    }
+
+    if args.split.is_some() && format == OutputFormat::Json {
+        bail!("--split does not support --json arrays; use --jsonl instead.");
+    }
+
+    if args.skeleton && !cfg!(feature = "skeleton") {
+        eprintln!("--skeleton is not available in this build; printing files in full.");
+    }
+
     let cfg = Config {
         exts: ext_set,
+        all_text,
+        binary: args.binary,
+        filenames,
         paths: args.paths,
         follow_symlinks: args.follow_symlinks,
 
         no_gitignore: args.no_gitignore,
-        json: args.json,
+        allow_sensitive: args.allow_sensitive,
+        format,
         excludes: args.excludes,
+        includes: args.includes,
         max_bytes: args.max_bytes,
         strip_comments: args.strip_comments,
+        strip_docstrings: args.strip_docstrings,
+        skeleton: args.skeleton,
+        compact: args.compact,
+        redact_secrets: args.redact_secrets,
+        fail_on_secret: args.fail_on_secret,
+        redactions: Redactions::load(&args.redact, args.redact_rules.as_deref())?,
+        keep_comments: args.keep_comments,
+        no_default_keep: args.no_default_keep,
+        languages,
         end_marker: args.end_marker,
+        summary: args.summary,
+        meta: args.meta,
+        stats: args.stats,
+        tokenizer: args.tokenizer,
+        budget: args.budget,
+        priority: args.priority,
+        prefer: args.prefer,
+        split: args.split,
+        out_dir: args.out_dir,
+        split_files: args.split_files,
+        tree: args.tree,
+        toc: args.toc,
+        line_numbers: args.line_numbers,
     };
 
     run_with_config(cfg)
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use sha2::{Digest, Sha256};
+
+use crate::lang::Language;
+
+/// Optional per-file metadata attached to JSON entries with `--meta`.
+///
+/// `size`, `lines` and `sha256` describe the file as it is on disk (before any
+/// comment stripping), so they can be checked against the original tree.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FileMeta {
+    pub size: u64,
+    pub lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub sha256: String,
+    /// Modification time in seconds since the Unix epoch, when available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    /// The root PATH (as given on the command line) this file was found under.
+    pub root: String,
+    /// Whether stripping, `--skeleton` or `--compact` changed any line of
+    /// `content`. False when none of them applies to the file.
+    pub stripped: bool,
+}
+
+impl FileMeta {
+    /// Collect metadata for `path`, whose raw contents are `bytes`.
+    pub fn collect(
+        path: &Path,
+        bytes: &[u8],
+        language: Option<&Language>,
+        root: &str,
+        stripped: bool,
+    ) -> Self {
+        let modified = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+
+        FileMeta {
+            size: bytes.len() as u64,
+            lines: count_lines(bytes),
+            language: language.map(|lang| lang.name.clone()),
+            sha256: sha256_hex(bytes),
+            modified,
+            root: root.to_string(),
+            stripped,
+        }
+    }
+}
+
+/// Count lines the way `wc -l` would, plus a final unterminated line.
+fn count_lines(bytes: &[u8]) -> usize {
+    let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+    match bytes.last() {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    }
+}
+
+/// Lowercase hex SHA-256 digest, matching `sha256sum` output.
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_lines_handles_missing_trailing_newline() {
+        assert_eq!(count_lines(b""), 0);
+        assert_eq!(count_lines(b"a\nb\n"), 2);
+        assert_eq!(count_lines(b"a\nb"), 2);
+    }
+
+    #[test]
+    fn sha256_hex_matches_known_digest() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
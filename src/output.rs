@@ -0,0 +1,288 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::binary::BinaryPolicy;
+use crate::meta::FileMeta;
+use crate::{Config, Document};
+
+/// How matched files are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// `========== FILE: path ==========` banners followed by raw content.
+    #[default]
+    Text,
+    /// A single JSON array of file objects.
+    Json,
+    /// One self-contained JSON file object per line (NDJSON).
+    Jsonl,
+    /// A `### path` heading followed by a fenced code block per file.
+    Markdown,
+    /// `<document>` elements inside a `<documents>` root, as recommended for
+    /// long-context LLM prompts.
+    Xml,
+}
+
+impl OutputFormat {
+    /// Text written before the first file section.
+    pub(crate) fn prologue(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "[\n",
+            OutputFormat::Xml => "<documents>\n",
+            OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Jsonl => "",
+        }
+    }
+
+    /// Text written between two file sections.
+    pub(crate) fn separator(self) -> &'static str {
+        match self {
+            OutputFormat::Json => ",\n",
+            _ => "",
+        }
+    }
+
+    /// Text written after the last file section.
+    pub(crate) fn epilogue(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "\n]\n",
+            OutputFormat::Xml => "</documents>\n",
+            OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Jsonl => "",
+        }
+    }
+
+    /// File extension used for `--split` part files.
+    pub(crate) fn file_ext(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Xml => "xml",
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct FileEntry<'a> {
+    path: &'a str,
+    file_name: String,
+    content: &'a str,
+    /// `[i, n]` when this entry is piece `i` of a file split across `n` parts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chunk: Option<[usize; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<usize>,
+    /// Original line number of each line in `content`, with `--line-numbers`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_numbers: Option<&'a [usize]>,
+    /// Detected format of a binary file, whose `content` is a placeholder or
+    /// base64.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    binary: Option<&'a str>,
+    /// `"base64"` when `content` is the base64-encoded file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'a str>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    meta: Option<FileMeta>,
+}
+
+/// Trailing totals record for JSON Lines output.
+#[derive(serde::Serialize)]
+struct SummaryRecord {
+    summary: Summary,
+}
+
+#[derive(serde::Serialize)]
+struct Summary {
+    files: usize,
+    bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<usize>,
+    errors: usize,
+}
+
+/// Render the JSON Lines `{"summary": {...}}` record (with trailing newline).
+pub(crate) fn render_summary(
+    files: usize,
+    bytes: u64,
+    tokens: Option<usize>,
+    errors: usize,
+) -> Result<String> {
+    let summary = SummaryRecord {
+        summary: Summary {
+            files,
+            bytes,
+            tokens,
+            errors,
+        },
+    };
+    Ok(format!("{}\n", serde_json::to_string(&summary)?))
+}
+
+/// Render one file section in the configured format. `index` is 1-based.
+pub(crate) fn render_document(cfg: &Config, doc: &Document, index: usize) -> Result<String> {
+    let mut out = String::new();
+
+    match cfg.format {
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let encoding =
+                (doc.binary.is_some() && cfg.binary == BinaryPolicy::Base64).then_some("base64");
+            write_file_json(&mut out, doc, encoding, doc.meta.clone())?;
+            if cfg.format == OutputFormat::Jsonl {
+                out.push('\n');
+            }
+        }
+        OutputFormat::Markdown => {
+            write_file_markdown(&mut out, doc, doc.language.as_deref().unwrap_or(""))?
+        }
+        OutputFormat::Xml => write_file_xml(&mut out, &doc.label(), &doc.text, index)?,
+        OutputFormat::Text => write_file(&mut out, &doc.label(), &doc.text, cfg.end_marker)?,
+    }
+
+    Ok(out)
+}
+
+/// Write a single file with header (and optional end marker).
+pub fn write_file(
+    out: &mut String,
+    display_path: &str,
+    text: &str,
+    end_marker: bool,
+) -> std::fmt::Result {
+    writeln!(out, "========== FILE: {} ==========", display_path)?;
+    out.push_str(text);
+
+    // Ensure there is a trailing newline before the separator between files.
+    if !text.ends_with('\n') {
+        out.push('\n');
+    }
+
+    if end_marker {
+        writeln!(out, "========== END FILE: {} ==========\n", display_path)?;
+    } else {
+        out.push('\n');
+    }
+
+    Ok(())
+}
+
+/// Write a single file as a compact JSON object (no trailing newline).
+fn write_file_json(
+    out: &mut String,
+    doc: &Document,
+    encoding: Option<&str>,
+    meta: Option<FileMeta>,
+) -> Result<()> {
+    let entry = FileEntry {
+        path: &doc.display_path,
+        file_name: doc
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        content: &doc.text,
+        chunk: doc.chunk.map(|(i, n)| [i, n]),
+        tokens: doc.tokens,
+        line_numbers: doc.line_numbers.as_deref(),
+        binary: doc.binary,
+        encoding,
+        meta,
+    };
+
+    out.push_str(&serde_json::to_string(&entry)?);
+
+    Ok(())
+}
+
+/// Write a single file as a Markdown section: a `### path` heading and a fenced
+/// code block tagged with the language `lang` (empty if unknown).
+fn write_file_markdown(out: &mut String, doc: &Document, lang: &str) -> std::fmt::Result {
+    let fence = markdown_fence(&doc.text);
+
+    writeln!(out, "### {}\n", doc.label())?;
+    writeln!(out, "{fence}{lang}")?;
+    out.push_str(&doc.text);
+
+    if !doc.text.ends_with('\n') {
+        out.push('\n');
+    }
+
+    writeln!(out, "{fence}\n")
+}
+
+/// Write a single file as an XML `<document>` element. The path is escaped and
+/// the content is wrapped in CDATA so code stays readable.
+pub fn write_file_xml(
+    out: &mut String,
+    display_path: &str,
+    text: &str,
+    index: usize,
+) -> std::fmt::Result {
+    writeln!(out, "<document index=\"{index}\">")?;
+    writeln!(out, "<source>{}</source>", xml_escape(display_path))?;
+    writeln!(
+        out,
+        "<document_content>{}</document_content>",
+        xml_cdata(text)
+    )?;
+    writeln!(out, "</document>")
+}
+
+/// Escape the characters that are significant in XML text and attributes.
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Wrap text in a CDATA section, splitting any `]]>` in the content across two
+/// sections so it cannot terminate the block early.
+pub(crate) fn xml_cdata(s: &str) -> String {
+    format!("<![CDATA[{}]]>", s.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Pick a backtick fence longer than any backtick run inside `text`, so the
+/// code block can never be closed early by the file's own content.
+pub(crate) fn markdown_fence(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+
+    for c in text.chars() {
+        if c == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+
+    "`".repeat((longest + 1).max(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_fence_grows_past_embedded_backticks() {
+        assert_eq!(markdown_fence("fn main() {}\n"), "```");
+        assert_eq!(markdown_fence("```rust\nx\n```\n"), "````");
+        assert_eq!(markdown_fence("`````"), "``````");
+    }
+
+    #[test]
+    fn xml_helpers_escape_and_split_cdata() {
+        assert_eq!(xml_escape("a<b>&\"c\""), "a&lt;b&gt;&amp;&quot;c&quot;");
+        assert_eq!(xml_cdata("x]]>y"), "<![CDATA[x]]]]><![CDATA[>y]]>");
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+/// Replacement used when a rule does not give one.
+pub const DEFAULT_REPLACEMENT: &str = "[REDACTED]";
+
+/// One `--redact` rule: matches of `regex` are replaced with `replacement`,
+/// which may refer to capture groups as `$1` or `${name}`.
+#[derive(Debug, Clone)]
+struct Rule {
+    regex: Regex,
+    replacement: String,
+    /// Only files whose display path matches one of these globs, if given.
+    paths: Option<GlobSet>,
+}
+
+/// User redaction rules from `--redact` and a `--redact-rules` file, applied
+/// in order.
+#[derive(Debug, Clone, Default)]
+pub struct Redactions {
+    rules: Vec<Rule>,
+}
+
+/// A `[[redact]]` entry in a rules file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    pattern: String,
+    #[serde(default)]
+    replacement: Option<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    redact: Vec<RuleEntry>,
+}
+
+impl Redactions {
+    /// Rules from the `REGEX[=replacement]` specs given on the command line,
+    /// followed by those in `path`, if given.
+    pub fn load(specs: &[String], path: Option<&Path>) -> Result<Redactions> {
+        let mut rules = specs
+            .iter()
+            .map(|spec| {
+                let (pattern, replacement) = split_spec(spec);
+                Rule::new(&pattern, replacement, &[])
+                    .with_context(|| format!("Invalid --redact rule: {spec}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(path) = path {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read redaction rules {}", path.display()))?;
+            let parsed = Redactions::parse(&text)
+                .with_context(|| format!("Invalid redaction rules {}", path.display()))?;
+            rules.extend(parsed.rules);
+        }
+
+        Ok(Redactions { rules })
+    }
+
+    /// Parse rules from TOML text with `[[redact]]` entries.
+    pub fn parse(text: &str) -> Result<Redactions> {
+        let file: RulesFile = toml::from_str(text)?;
+        let rules = file
+            .redact
+            .iter()
+            .map(|entry| {
+                Rule::new(&entry.pattern, entry.replacement.as_deref(), &entry.paths)
+                    .with_context(|| format!("Invalid rule: {}", entry.pattern))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Redactions { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Apply every rule scoped to `display_path` to `text`. Returns `None`
+    /// when nothing matched. A match spanning several lines is followed by
+    /// the newlines it removed, so line numbers still match the file.
+    pub fn apply(&self, display_path: &str, text: &str) -> Option<String> {
+        let mut out: Option<String> = None;
+
+        for rule in &self.rules {
+            if rule
+                .paths
+                .as_ref()
+                .is_some_and(|paths| !paths.is_match(display_path))
+            {
+                continue;
+            }
+
+            let current = out.as_deref().unwrap_or(text);
+            if rule.regex.is_match(current) {
+                let replaced = rule.regex.replace_all(current, |caps: &Captures| {
+                    let mut dst = String::new();
+                    caps.expand(&rule.replacement, &mut dst);
+                    let newlines = caps[0].matches('\n').count();
+                    let missing = newlines.saturating_sub(dst.matches('\n').count());
+                    dst.extend(std::iter::repeat_n('\n', missing));
+                    dst
+                });
+                out = Some(replaced.into_owned());
+            }
+        }
+
+        out
+    }
+}
+
+impl Rule {
+    fn new(pattern: &str, replacement: Option<&str>, paths: &[String]) -> Result<Rule> {
+        let regex = Regex::new(pattern)?;
+
+        let paths = if paths.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pat in paths {
+                builder.add(Glob::new(pat).with_context(|| format!("Invalid glob: {pat}"))?);
+            }
+            Some(builder.build()?)
+        };
+
+        Ok(Rule {
+            regex,
+            replacement: replacement.unwrap_or(DEFAULT_REPLACEMENT).to_string(),
+            paths,
+        })
+    }
+}
+
+/// Split a `REGEX[=replacement]` spec at the first `=` not escaped as `\=`.
+/// The escape is left in the pattern, where it still means a literal `=`.
+fn split_spec(spec: &str) -> (String, Option<&str>) {
+    let mut escaped = false;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '=' if !escaped => return (spec[..i].to_string(), Some(&spec[i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    (spec.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_specs_split_at_the_first_unescaped_equals() -> Result<()> {
+        assert_eq!(
+            split_spec(r"acme\.internal"),
+            (r"acme\.internal".into(), None)
+        );
+        assert_eq!(split_spec("a=b=c"), ("a".into(), Some("b=c")));
+        assert_eq!(
+            split_spec(r"key\=\d+=key=N"),
+            (r"key\=\d+".into(), Some("key=N"))
+        );
+
+        let rules = Redactions::load(
+            &[r"key\=(\d+)=key=<$1>".to_string(), r"CUST-\d+".to_string()],
+            None,
+        )?;
+        assert_eq!(
+            rules.apply("a.txt", "key=42 CUST-1234").as_deref(),
+            Some("key=<42> [REDACTED]")
+        );
+        assert_eq!(rules.apply("a.txt", "nothing here"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_line_matches_keep_their_newlines() -> Result<()> {
+        let rules = Redactions::load(&[r"(?s)BEGIN.*?END=<cut>".to_string()], None)?;
+        assert_eq!(
+            rules
+                .apply("a.txt", "a\nBEGIN\nx\ny\nEND b\nc\n")
+                .as_deref(),
+            Some("a\n<cut>\n\n\n b\nc\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn file_rules_support_named_groups_and_path_scoping() -> Result<()> {
+        let rules = Redactions::parse(
+            r#"
+            [[redact]]
+            pattern = '(?P<user>[a-z.]+)@corp\.example'
+            replacement = "${user}@example.com"
+
+            [[redact]]
+            pattern = '\bdb\d+\.prod\b'
+            replacement = "db.host"
+            paths = ["deploy/**", "*.yaml"]
+            "#,
+        )?;
+
+        let text = "ann.lee@corp.example db7.prod\n";
+        assert_eq!(
+            rules.apply("deploy/app.cfg", text).as_deref(),
+            Some("ann.lee@example.com db.host\n")
+        );
+        assert_eq!(
+            rules.apply("src/main.rs", text).as_deref(),
+            Some("ann.lee@example.com db7.prod\n")
+        );
+
+        assert!(Redactions::parse("[[redact]]\npattern = '('\n").is_err());
+        assert!(Redactions::parse("[[redact]]\npattern = 'x'\nglob = 1\n").is_err());
+        Ok(())
+    }
+}
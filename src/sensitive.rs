@@ -0,0 +1,84 @@
+use std::path::Path;
+use std::sync::LazyLock;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// File names that are never printed unless `--allow-sensitive` is given,
+/// matched case-insensitively against the file name only.
+pub const SENSITIVE_FILES: &[&str] = &[
+    // Environment files with credentials.
+    ".env",
+    ".env.*",
+    // Private keys and certificate / key stores.
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.jks",
+    "*.keystore",
+    // Password databases.
+    "*.kdbx",
+    ".htpasswd",
+    // Tool credentials: cloud SDKs, package registries, git, netrc.
+    "credentials",
+    "credentials.json",
+    ".npmrc",
+    ".pypirc",
+    ".netrc",
+    ".git-credentials",
+    // Terraform state stores resource secrets in plain text.
+    "*.tfstate",
+    "*.tfstate.*",
+];
+
+static DENYLIST: LazyLock<GlobSet> = LazyLock::new(|| {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in SENSITIVE_FILES {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .expect("built-in sensitive file pattern is valid");
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .expect("built-in sensitive file patterns are valid")
+});
+
+/// Whether the file name of `path` is on the built-in denylist.
+pub fn is_sensitive(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| DENYLIST.is_match(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_file_names_case_insensitively() {
+        for path in [
+            ".env",
+            "app/.env.production",
+            "home/.ssh/id_rsa",
+            "certs/Server.PEM",
+            "vault.kdbx",
+            ".aws/credentials",
+            "infra/terraform.tfstate.backup",
+        ] {
+            assert!(is_sensitive(Path::new(path)), "{path}");
+        }
+
+        for path in [
+            "src/env.rs",
+            "home/.ssh/id_rsa.pub",
+            "docs/keys.md",
+            "credentials.py",
+        ] {
+            assert!(!is_sensitive(Path::new(path)), "{path}");
+        }
+    }
+}
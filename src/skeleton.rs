@@ -0,0 +1,269 @@
+/// Whether `--skeleton` can outline files of the registry language `name`.
+pub fn supports(name: &str) -> bool {
+    grammar(name).is_some()
+}
+
+/// The outline of `src`, written in the registry language `name`, as lines
+/// paired with their 1-based line numbers in `src`. Imports, type
+/// definitions, signatures and everything else outside function bodies are
+/// kept verbatim. Returns `None` for unsupported languages.
+pub fn outline(src: &str, name: &str) -> Option<Vec<(usize, String)>> {
+    let grammar = grammar(name)?;
+    let bodies = grammar.bodies(src)?;
+
+    let mut out = Vec::new();
+    let mut line = String::new();
+    let mut line_no = 1;
+    // Line in `src` that `pos` is on, kept up to date while walking forward
+    // so that nothing is rescanned.
+    let mut src_line = 1;
+    let mut pos = 0;
+
+    let mut push_kept =
+        |text: &str, line: &mut String, line_no: &mut usize, src_line: &mut usize| {
+            for c in text.chars() {
+                if c == '\n' {
+                    out.push((*line_no, std::mem::take(line)));
+                    *src_line += 1;
+                    *line_no = *src_line;
+                } else {
+                    line.push(c);
+                }
+            }
+        };
+
+    for (start, end) in bodies {
+        push_kept(&src[pos..start], &mut line, &mut line_no, &mut src_line);
+        line.push_str(grammar.placeholder);
+        src_line += src[start..end].matches('\n').count();
+        pos = end;
+    }
+    push_kept(&src[pos..], &mut line, &mut line_no, &mut src_line);
+
+    if !line.is_empty() {
+        out.push((line_no, line));
+    }
+
+    Some(out)
+}
+
+/// How to find function bodies in one language.
+#[cfg_attr(not(feature = "skeleton"), allow(dead_code))]
+struct Grammar {
+    #[cfg(feature = "skeleton")]
+    language: tree_sitter::Language,
+    /// Node kinds whose `body` field is elided.
+    functions: &'static [&'static str],
+    /// Body node kinds that may be elided (e.g. not an arrow function's
+    /// expression body).
+    body_kinds: &'static [&'static str],
+    placeholder: &'static str,
+}
+
+#[cfg(feature = "skeleton")]
+const BRACES: &str = "{ ... }";
+
+#[cfg(feature = "skeleton")]
+fn grammar(name: &str) -> Option<Grammar> {
+    let js_functions: &[&str] = &[
+        "function_declaration",
+        "generator_function_declaration",
+        "function_expression",
+        "function",
+        "generator_function",
+        "arrow_function",
+        "method_definition",
+    ];
+
+    let (language, functions, body_kinds, placeholder): (tree_sitter::Language, _, &[&str], _) =
+        match name {
+            "rust" => (
+                tree_sitter_rust::LANGUAGE.into(),
+                &["function_item"][..],
+                &["block"],
+                BRACES,
+            ),
+            "python" => (
+                tree_sitter_python::LANGUAGE.into(),
+                &["function_definition"][..],
+                &["block"],
+                "...",
+            ),
+            "go" => (
+                tree_sitter_go::LANGUAGE.into(),
+                &["function_declaration", "method_declaration"][..],
+                &["block"],
+                BRACES,
+            ),
+            "java" => (
+                tree_sitter_java::LANGUAGE.into(),
+                &["method_declaration", "constructor_declaration"][..],
+                &["block", "constructor_body"],
+                BRACES,
+            ),
+            "c" => (
+                tree_sitter_c::LANGUAGE.into(),
+                &["function_definition"][..],
+                &["compound_statement"],
+                BRACES,
+            ),
+            "cpp" => (
+                tree_sitter_cpp::LANGUAGE.into(),
+                &["function_definition", "lambda_expression"][..],
+                &["compound_statement"],
+                BRACES,
+            ),
+            "javascript" | "jsx" => (
+                tree_sitter_javascript::LANGUAGE.into(),
+                js_functions,
+                &["statement_block"],
+                BRACES,
+            ),
+            "typescript" => (
+                tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+                js_functions,
+                &["statement_block"],
+                BRACES,
+            ),
+            "tsx" => (
+                tree_sitter_typescript::LANGUAGE_TSX.into(),
+                js_functions,
+                &["statement_block"],
+                BRACES,
+            ),
+            _ => return None,
+        };
+
+    Some(Grammar {
+        language,
+        functions,
+        body_kinds,
+        placeholder,
+    })
+}
+
+#[cfg(not(feature = "skeleton"))]
+fn grammar(_name: &str) -> Option<Grammar> {
+    None
+}
+
+impl Grammar {
+    /// Byte ranges of the outermost function bodies in `src`, in order.
+    #[cfg(feature = "skeleton")]
+    fn bodies(&self, src: &str) -> Option<Vec<(usize, usize)>> {
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&self.language).ok()?;
+        let tree = parser.parse(src, None)?;
+
+        let mut bodies = Vec::new();
+        let mut stack = vec![tree.root_node()];
+
+        while let Some(node) = stack.pop() {
+            if self.functions.contains(&node.kind())
+                && let Some(body) = node.child_by_field_name("body")
+                && self.body_kinds.contains(&body.kind())
+            {
+                bodies.push((body.start_byte(), body.end_byte()));
+                continue;
+            }
+
+            let mut cursor = node.walk();
+            let children: Vec<_> = node.children(&mut cursor).collect();
+            stack.extend(children.into_iter().rev());
+        }
+
+        Some(bodies)
+    }
+
+    #[cfg(not(feature = "skeleton"))]
+    fn bodies(&self, _src: &str) -> Option<Vec<(usize, usize)>> {
+        None
+    }
+}
+
+#[cfg(all(test, feature = "skeleton"))]
+mod tests {
+    use super::*;
+
+    fn text(lines: Vec<(usize, String)>) -> String {
+        lines.into_iter().map(|(_, line)| line + "\n").collect()
+    }
+
+    #[test]
+    fn rust_keeps_items_and_elides_bodies() {
+        let src = r#"use std::fmt;
+
+/// A point.
+pub struct Point {
+    x: i32,
+}
+
+impl Point {
+    pub fn new(x: i32) -> Self {
+        let p = Point { x };
+        p
+    }
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+}
+"#;
+        let expected = r#"use std::fmt;
+
+/// A point.
+pub struct Point {
+    x: i32,
+}
+
+impl Point {
+    pub fn new(x: i32) -> Self { ... }
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+}
+"#;
+        let out = outline(src, "rust").unwrap();
+        assert_eq!(text(out.clone()), expected);
+
+        // The closing `}` of `impl` comes from line 13 of the source.
+        assert_eq!(out[8], (9, "    pub fn new(x: i32) -> Self { ... }".into()));
+        assert_eq!(out[9], (13, "}".into()));
+    }
+
+    #[test]
+    fn python_bodies_become_ellipsis() {
+        let src = "import os\n\n\nclass A:\n    x = 1\n\n    def f(self, y):\n        return y\n\n\nasync def main():\n    await run()\n";
+        let expected = "import os\n\n\nclass A:\n    x = 1\n\n    def f(self, y):\n        ...\n\n\nasync def main():\n    ...\n";
+        assert_eq!(text(outline(src, "python").unwrap()), expected);
+    }
+
+    #[test]
+    fn typescript_elides_functions_methods_and_arrow_blocks() {
+        let src = "import { a } from './a';\nexport interface I { f(): void }\nexport class C {\n  m(x: number): number {\n    return x;\n  }\n}\nexport const g = (y: string) => {\n  return y;\n};\nexport const h = (z: number) => z + 1;\n";
+        let expected = "import { a } from './a';\nexport interface I { f(): void }\nexport class C {\n  m(x: number): number { ... }\n}\nexport const g = (y: string) => { ... };\nexport const h = (z: number) => z + 1;\n";
+        assert_eq!(text(outline(src, "typescript").unwrap()), expected);
+    }
+
+    #[test]
+    fn go_and_c_bodies_are_elided() {
+        let src = "package main\n\nfunc (s *S) Run() error {\n\treturn nil\n}\n";
+        assert_eq!(
+            text(outline(src, "go").unwrap()),
+            "package main\n\nfunc (s *S) Run() error { ... }\n"
+        );
+
+        let src = "#include <stdio.h>\nint main(void) {\n  return 0;\n}\n";
+        assert_eq!(
+            text(outline(src, "c").unwrap()),
+            "#include <stdio.h>\nint main(void) { ... }\n"
+        );
+    }
+
+    #[test]
+    fn unsupported_languages_are_left_alone() {
+        assert!(outline("x = 1\n", "ruby").is_none());
+        assert!(!supports("ruby"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::budget::Budget;
+use crate::output::{OutputFormat, render_document};
+use crate::{Config, Document};
+
+/// Placeholder part numbers used when estimating header sizes before the
+/// final part count is known.
+const PLACEHOLDER_PART: usize = 999;
+
+/// Write `docs` as `part-001.<ext>`, `part-002.<ext>`, ... in `out_dir`, each
+/// under `limit` where possible. `preamble` sections (e.g. the `--tree`)
+/// open the first part.
+///
+/// A file's header is never separated from its content. A single file larger
+/// than a part either gets a part of its own (with a warning), or, with
+/// `cfg.split_files`, is broken on line boundaries into chunks that each carry
+/// a `(chunk i of n)` header. Part files left in `out_dir` by an earlier run
+/// are removed first. Returns the number of parts written.
+pub(crate) fn write_parts(
+    cfg: &Config,
+    docs: Vec<Document>,
+    limit: Budget,
+    out_dir: &Path,
+    preamble: &[String],
+    summary: Option<&str>,
+) -> Result<usize> {
+    let measure = |text: &str| limit.measure(text, cfg.tokenizer);
+
+    // Fixed cost every part pays regardless of its contents. The preamble and
+    // summary only appear once, but are budgeted for in every part so no part
+    // can overflow because of them.
+    let header = part_header(cfg.format, PLACEHOLDER_PART, PLACEHOLDER_PART, &[]);
+    let frame = measure(&header)
+        + measure(cfg.format.prologue())
+        + measure(cfg.format.epilogue())
+        + preamble
+            .iter()
+            .map(|s| measure(s) + measure(cfg.format.separator()))
+            .sum::<u64>()
+        + summary.map(measure).unwrap_or(0);
+
+    let docs = if cfg.split_files {
+        docs.into_iter()
+            .flat_map(|doc| chunk_document(cfg, doc, limit, frame))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        docs
+    };
+
+    // Greedily pack rendered sections into parts.
+    let mut parts: Vec<Vec<(String, String)>> = Vec::new();
+    let mut current: Vec<(String, String)> = Vec::new();
+    let mut used = frame;
+
+    for (index, doc) in docs.iter().enumerate() {
+        let label = doc.label();
+        let section = render_document(cfg, doc, index + 1)?;
+        let cost = measure(&section)
+            + measure(&file_list_line(cfg.format, &label))
+            + measure(cfg.format.separator());
+
+        if !current.is_empty() && used + cost > limit.limit() {
+            parts.push(std::mem::take(&mut current));
+            used = frame;
+        }
+
+        if frame + cost > limit.limit() {
+            eprintln!(
+                "Warning: {} ({} {}) does not fit in a single part",
+                label,
+                cost,
+                limit.unit()
+            );
+        }
+
+        used += cost;
+        current.push((label, section));
+    }
+
+    if !current.is_empty() || parts.is_empty() {
+        parts.push(current);
+    }
+
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create output directory {}", out_dir.display()))?;
+    remove_stale_parts(out_dir, cfg.format.file_ext())?;
+
+    let total = parts.len();
+    for (i, part) in parts.iter().enumerate() {
+        let labels: Vec<&str> = part.iter().map(|(label, _)| label.as_str()).collect();
+
+        let mut content = part_header(cfg.format, i + 1, total, &labels);
+        content.push_str(cfg.format.prologue());
+
+        let mut sections: Vec<&str> = Vec::new();
+        if i == 0 {
+            sections.extend(preamble.iter().map(String::as_str));
+        }
+        sections.extend(part.iter().map(|(_, section)| section.as_str()));
+        content.push_str(&sections.join(cfg.format.separator()));
+        content.push_str(cfg.format.epilogue());
+
+        if i + 1 == total
+            && let Some(summary) = summary
+        {
+            content.push_str(summary);
+        }
+
+        let file = out_dir.join(format!("part-{:03}.{}", i + 1, cfg.format.file_ext()));
+        fs::write(&file, content).with_context(|| format!("Failed to write {}", file.display()))?;
+    }
+
+    eprintln!("Wrote {} parts to {}", total, out_dir.display());
+
+    Ok(total)
+}
+
+/// Delete `part-NNN.<ext>` files in `dir`, so parts from an earlier run with
+/// more parts don't linger next to the new ones.
+fn remove_stale_parts(dir: &Path, ext: &str) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+
+    for entry in entries {
+        let path = entry?.path();
+        let is_part = path.extension().is_some_and(|e| e == ext)
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix("part-"))
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if is_part && path.is_file() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Break a document that cannot fit in one part into line-aligned chunks.
+/// Documents that already fit are returned unchanged.
+fn chunk_document(cfg: &Config, doc: Document, limit: Budget, frame: u64) -> Vec<Result<Document>> {
+    let measure = |text: &str| limit.measure(text, cfg.tokenizer);
+
+    let whole = match render_document(cfg, &doc, PLACEHOLDER_PART) {
+        Ok(section) => measure(&section) + measure(&file_list_line(cfg.format, &doc.label())),
+        Err(err) => return vec![Err(err)],
+    };
+    if frame + whole <= limit.limit() {
+        return vec![Ok(doc)];
+    }
+
+    // Cost of a chunk's header and list entry with an empty body.
+    let mut empty = doc.clone();
+    empty.text = String::new();
+    empty.chunk = Some((PLACEHOLDER_PART, PLACEHOLDER_PART));
+    let overhead = match render_document(cfg, &empty, PLACEHOLDER_PART) {
+        Ok(section) => measure(&section) + measure(&file_list_line(cfg.format, &empty.label())),
+        Err(err) => return vec![Err(err)],
+    };
+    let room = limit.limit().saturating_sub(frame + overhead);
+
+    let mut pieces: Vec<String> = Vec::new();
+    let mut piece = String::new();
+    let mut piece_cost = 0;
+    for line in doc.text.split_inclusive('\n') {
+        let cost = measure(line);
+        if !piece.is_empty() && piece_cost + cost > room {
+            pieces.push(std::mem::take(&mut piece));
+            piece_cost = 0;
+        }
+        piece.push_str(line);
+        piece_cost += cost;
+    }
+    if !piece.is_empty() {
+        pieces.push(piece);
+    }
+
+    let n = pieces.len();
+    let mut first_line = 0;
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let mut chunk = doc.clone();
+            let line_count = text.lines().count();
+            chunk.line_numbers = doc.line_numbers.as_ref().map(|numbers| {
+                let end = (first_line + line_count).min(numbers.len());
+                numbers[first_line.min(end)..end].to_vec()
+            });
+            first_line += line_count;
+            chunk.tokens = doc
+                .tokens
+                .map(|_| crate::tokens::count_tokens(&text, cfg.tokenizer));
+            chunk.text = text;
+            chunk.chunk = Some((i + 1, n));
+            Ok(chunk)
+        })
+        .collect()
+}
+
+/// Leading `{"part": {...}}` record of a JSON Lines part.
+#[derive(serde::Serialize)]
+struct PartRecord<'a> {
+    part: PartInfo<'a>,
+}
+
+#[derive(serde::Serialize)]
+struct PartInfo<'a> {
+    index: usize,
+    total: usize,
+    files: &'a [&'a str],
+}
+
+/// The `part i of n` header listing the files a part contains.
+fn part_header(format: OutputFormat, index: usize, total: usize, labels: &[&str]) -> String {
+    let mut out = String::new();
+
+    match format {
+        OutputFormat::Text => {
+            let _ = writeln!(out, "========== PART {index} of {total} ==========");
+            for label in labels {
+                out.push_str(&file_list_line(format, label));
+            }
+            out.push('\n');
+        }
+        OutputFormat::Markdown => {
+            let _ = writeln!(out, "## Part {index} of {total}\n");
+            for label in labels {
+                out.push_str(&file_list_line(format, label));
+            }
+            out.push('\n');
+        }
+        OutputFormat::Xml => {
+            let _ = writeln!(out, "<!-- Part {index} of {total}");
+            for label in labels {
+                out.push_str(&file_list_line(format, label));
+            }
+            out.push_str("-->\n");
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let record = PartRecord {
+                part: PartInfo {
+                    index,
+                    total,
+                    files: labels,
+                },
+            };
+            let json = serde_json::to_string(&record).unwrap_or_default();
+            let _ = writeln!(out, "{json}");
+        }
+    }
+
+    out
+}
+
+/// One entry of a part header's file list.
+fn file_list_line(format: OutputFormat, label: &str) -> String {
+    match format {
+        OutputFormat::Text => format!("  {label}\n"),
+        OutputFormat::Markdown => format!("- `{label}`\n"),
+        // XML comments may not contain `--`.
+        OutputFormat::Xml => format!("  {}\n", label.replace("--", "- -")),
+        // Quoted path plus a comma in the JSON `files` array.
+        OutputFormat::Json | OutputFormat::Jsonl => format!("\"{label}\","),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_header_lists_files() {
+        let header = part_header(OutputFormat::Text, 2, 3, &["src/a.rs", "src/b.rs"]);
+        assert_eq!(
+            header,
+            "========== PART 2 of 3 ==========\n  src/a.rs\n  src/b.rs\n\n"
+        );
+
+        let header = part_header(OutputFormat::Jsonl, 1, 1, &["a.py"]);
+        assert_eq!(
+            header,
+            "{\"part\":{\"index\":1,\"total\":1,\"files\":[\"a.py\"]}}\n"
+        );
+    }
+}
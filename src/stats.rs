@@ -0,0 +1,53 @@
+use crate::tokens::Tokenizer;
+
+/// Per-file figures collected for the `--stats` report.
+#[derive(Debug, Clone)]
+pub struct FileStat {
+    pub path: String,
+    pub bytes: u64,
+    pub tokens: usize,
+}
+
+/// Totals for a run, printed to stderr with `--stats`.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub files: Vec<FileStat>,
+    /// Files left out because they look binary.
+    pub skipped_binary: usize,
+}
+
+impl Stats {
+    pub fn push(&mut self, path: &str, bytes: u64, tokens: usize) {
+        self.files.push(FileStat {
+            path: path.to_string(),
+            bytes,
+            tokens,
+        });
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.files.iter().map(|f| f.tokens).sum()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.bytes).sum()
+    }
+
+    /// Print a per-file table followed by a totals line to stderr.
+    pub fn report(&self, tokenizer: Tokenizer) {
+        eprintln!("{:>10} {:>10}  path", "tokens", "bytes");
+        for f in &self.files {
+            eprintln!("{:>10} {:>10}  {}", f.tokens, f.bytes, f.path);
+        }
+        eprintln!(
+            "Total: {} files, {} tokens, {} bytes (tokenizer: {})",
+            self.files.len(),
+            self.total_tokens(),
+            self.total_bytes(),
+            tokenizer.name()
+        );
+        if self.skipped_binary > 0 {
+            eprintln!("Skipped: {} binary files", self.skipped_binary);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt::Write as _;
+
+use anyhow::Result;
+
+use crate::Document;
+use crate::output::{OutputFormat, xml_escape};
+
+/// One row of the `--toc` table.
+#[derive(Debug, serde::Serialize)]
+struct TocEntry<'a> {
+    path: &'a str,
+    bytes: usize,
+    lines: usize,
+    /// 1-based line in the combined output where the file's section begins.
+    line: usize,
+}
+
+#[derive(serde::Serialize)]
+struct TocRecord<'a> {
+    toc: &'a [TocEntry<'a>],
+}
+
+/// Render the `--toc` section, which is printed after any other `preamble`
+/// sections and before the file `sections`.
+///
+/// Line numbers refer to the combined stdout output, so they are computed
+/// from everything printed ahead of each section, including the table itself.
+pub(crate) fn render(
+    format: OutputFormat,
+    docs: &[Document],
+    preamble: &[String],
+    sections: &[String],
+) -> Result<String> {
+    let mut entries: Vec<TocEntry> = docs
+        .iter()
+        .map(|doc| TocEntry {
+            path: &doc.display_path,
+            bytes: doc.text.len(),
+            lines: doc.text.lines().count(),
+            line: 0,
+        })
+        .collect();
+
+    // The table's own height doesn't depend on the numbers in it, so render
+    // it once with placeholders to find where the first file starts.
+    let toc_height = newlines(&render_entries(format, &entries)?);
+    let sep = newlines(format.separator());
+
+    let mut offset = newlines(format.prologue())
+        + preamble.iter().map(|s| newlines(s) + sep).sum::<usize>()
+        + toc_height
+        + sep;
+
+    for (entry, section) in entries.iter_mut().zip(sections) {
+        entry.line = offset + 1;
+        offset += newlines(section) + sep;
+    }
+
+    render_entries(format, &entries)
+}
+
+fn render_entries(format: OutputFormat, entries: &[TocEntry]) -> Result<String> {
+    let mut out = String::new();
+
+    match format {
+        OutputFormat::Text => {
+            writeln!(out, "========== TABLE OF CONTENTS ==========")?;
+            writeln!(out, "{:>8} {:>10} {:>8}  path", "line", "bytes", "lines")?;
+            for e in entries {
+                writeln!(
+                    out,
+                    "{:>8} {:>10} {:>8}  {}",
+                    e.line, e.bytes, e.lines, e.path
+                )?;
+            }
+            out.push('\n');
+        }
+        OutputFormat::Markdown => {
+            writeln!(out, "### Table of contents\n")?;
+            writeln!(out, "| Line | Bytes | Lines | Path |")?;
+            writeln!(out, "|---:|---:|---:|---|")?;
+            for e in entries {
+                writeln!(
+                    out,
+                    "| {} | {} | {} | `{}` |",
+                    e.line, e.bytes, e.lines, e.path
+                )?;
+            }
+            out.push('\n');
+        }
+        OutputFormat::Xml => {
+            writeln!(out, "<table_of_contents>")?;
+            for e in entries {
+                writeln!(
+                    out,
+                    "<entry line=\"{}\" bytes=\"{}\" lines=\"{}\">{}</entry>",
+                    e.line,
+                    e.bytes,
+                    e.lines,
+                    xml_escape(e.path)
+                )?;
+            }
+            writeln!(out, "</table_of_contents>")?;
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            out.push_str(&serde_json::to_string(&TocRecord { toc: entries })?);
+            if format == OutputFormat::Jsonl {
+                out.push('\n');
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn newlines(s: &str) -> usize {
+    s.bytes().filter(|&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn doc(path: &str, text: &str) -> Document {
+        Document {
+            path: PathBuf::from(path),
+            display_path: path.to_string(),
+            root: ".".to_string(),
+            language: None,
+            text: text.to_string(),
+            tokens: None,
+            line_numbers: None,
+            chunk: None,
+            binary: None,
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn toc_line_numbers_point_at_section_headers() {
+        let docs = vec![doc("a.py", "a\nb\n"), doc("b.py", "c\n")];
+        let sections = vec![
+            "== a.py\na\nb\n\n".to_string(),
+            "== b.py\nc\n\n".to_string(),
+        ];
+        let toc = render(OutputFormat::Text, &docs, &[], &sections).unwrap();
+
+        // Header, column names, two rows and a blank line.
+        let first = 5 + 1;
+        let second = first + 4;
+        assert!(toc.contains(&format!("{first:>8} {:>10} {:>8}  a.py", 4, 2)));
+        assert!(toc.contains(&format!("{second:>8} {:>10} {:>8}  b.py", 2, 1)));
+    }
+}
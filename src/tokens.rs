@@ -0,0 +1,66 @@
+/// Tokenizer used to estimate how many model tokens a file costs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Tokenizer {
+    /// `o200k_base` BPE vocabulary (GPT-4o family).
+    #[default]
+    O200k,
+    /// `cl100k_base` BPE vocabulary (GPT-4 / GPT-3.5 family).
+    Cl100k,
+    /// Cheap estimate of one token per four characters.
+    Approx,
+}
+
+impl Tokenizer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tokenizer::O200k => "o200k",
+            Tokenizer::Cl100k => "cl100k",
+            Tokenizer::Approx => "approx",
+        }
+    }
+}
+
+/// Count tokens in `text` with the given tokenizer.
+///
+/// The BPE vocabularies are embedded in the binary (no network access). When
+/// pc is built without the `bpe` feature, every tokenizer falls back to the
+/// chars/4 estimate.
+pub fn count_tokens(text: &str, tokenizer: Tokenizer) -> usize {
+    match tokenizer {
+        Tokenizer::Approx => approx_tokens(text),
+        #[cfg(feature = "bpe")]
+        Tokenizer::O200k => tiktoken_rs::o200k_base_singleton()
+            .encode_ordinary(text)
+            .len(),
+        #[cfg(feature = "bpe")]
+        Tokenizer::Cl100k => tiktoken_rs::cl100k_base_singleton()
+            .encode_ordinary(text)
+            .len(),
+        #[cfg(not(feature = "bpe"))]
+        Tokenizer::O200k | Tokenizer::Cl100k => approx_tokens(text),
+    }
+}
+
+/// Estimate tokens as one per four characters, rounded up.
+pub fn approx_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approx_tokens_rounds_up() {
+        assert_eq!(approx_tokens(""), 0);
+        assert_eq!(approx_tokens("abcd"), 1);
+        assert_eq!(approx_tokens("abcde"), 2);
+    }
+
+    #[cfg(feature = "bpe")]
+    #[test]
+    fn bpe_tokenizers_count_words() {
+        assert_eq!(count_tokens("hello world", Tokenizer::O200k), 2);
+        assert_eq!(count_tokens("hello world", Tokenizer::Cl100k), 2);
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use anyhow::Result;
+
+use crate::Document;
+use crate::output::{OutputFormat, markdown_fence, xml_cdata};
+
+/// A directory (or file, when it has no children) in the `--tree` preamble.
+#[derive(Debug, Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, display_path: &str) {
+        let mut node = self;
+        for part in display_path.split('/').filter(|p| !p.is_empty()) {
+            node = node.children.entry(part.to_string()).or_default();
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        if self.children.is_empty() {
+            return serde_json::Value::Null;
+        }
+        let map = self
+            .children
+            .iter()
+            .map(|(name, child)| (name.clone(), child.to_json()))
+            .collect();
+        serde_json::Value::Object(map)
+    }
+}
+
+/// Build one tree per root, in the order roots first appear in `docs`.
+fn build(docs: &[Document]) -> Vec<(String, Node)> {
+    let mut roots: Vec<(String, Node)> = Vec::new();
+
+    for doc in docs {
+        let idx = match roots.iter().position(|(root, _)| *root == doc.root) {
+            Some(i) => i,
+            None => {
+                roots.push((doc.root.clone(), Node::default()));
+                roots.len() - 1
+            }
+        };
+        roots[idx].1.insert(&doc.display_path);
+    }
+
+    roots
+}
+
+/// Render the tree of `docs` as ASCII art, like the `tree` command.
+fn render_ascii(roots: &[(String, Node)]) -> String {
+    let mut out = String::new();
+    for (root, node) in roots {
+        out.push_str(root);
+        out.push('\n');
+        render_children(node, "", &mut out);
+    }
+    out
+}
+
+fn render_children(node: &Node, prefix: &str, out: &mut String) {
+    let n = node.children.len();
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let last = i + 1 == n;
+        let branch = if last { "└── " } else { "├── " };
+        let _ = writeln!(out, "{prefix}{branch}{name}");
+
+        if !child.children.is_empty() {
+            let indent = if last { "    " } else { "│   " };
+            render_children(child, &format!("{prefix}{indent}"), out);
+        }
+    }
+}
+
+/// Render the `--tree` preamble section for the emitted `docs`.
+///
+/// Text, Markdown and XML get an ASCII tree; JSON and JSON Lines get a
+/// `{"tree": {...}}` record of nested objects keyed by root, where files are
+/// `null`.
+pub(crate) fn render(format: OutputFormat, docs: &[Document]) -> Result<String> {
+    let roots = build(docs);
+
+    let out = match format {
+        OutputFormat::Text => format!("========== TREE ==========\n{}\n", render_ascii(&roots)),
+        OutputFormat::Markdown => {
+            let ascii = render_ascii(&roots);
+            let fence = markdown_fence(&ascii);
+            format!("### Directory tree\n\n{fence}text\n{ascii}{fence}\n\n")
+        }
+        OutputFormat::Xml => format!(
+            "<directory_tree>{}</directory_tree>\n",
+            xml_cdata(&render_ascii(&roots))
+        ),
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let map = roots
+                .iter()
+                .map(|(root, node)| (root.clone(), node.to_json()))
+                .collect();
+            let record = serde_json::json!({ "tree": serde_json::Value::Object(map) });
+            let mut out = serde_json::to_string(&record)?;
+            if format == OutputFormat::Jsonl {
+                out.push('\n');
+            }
+            out
+        }
+    };
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn doc(root: &str, path: &str) -> Document {
+        Document {
+            path: PathBuf::from(path),
+            display_path: path.to_string(),
+            root: root.to_string(),
+            language: None,
+            text: String::new(),
+            tokens: None,
+            line_numbers: None,
+            chunk: None,
+            binary: None,
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn render_ascii_matches_tree_layout() {
+        let docs = vec![
+            doc(".", "src/main.rs"),
+            doc(".", "src/lib.rs"),
+            doc(".", "tests/cli.rs"),
+            doc(".", "Cargo.toml"),
+        ];
+        let expected = "\
+.
+├── Cargo.toml
+├── src
+│   ├── lib.rs
+│   └── main.rs
+└── tests
+    └── cli.rs
+";
+        assert_eq!(render_ascii(&build(&docs)), expected);
+    }
+
+    #[test]
+    fn render_json_nests_directories() {
+        let docs = vec![doc(".", "src/main.rs")];
+        let out = render(OutputFormat::Jsonl, &docs).unwrap();
+        assert_eq!(out, "{\"tree\":{\".\":{\"src\":{\"main.rs\":null}}}}\n");
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Result, bail};
+
+use crate::lang::Registry;
+
+/// The files selected by one `--type` name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileType {
+    /// Lowercase extensions without the dot.
+    pub extensions: Vec<String>,
+    /// Exact file names, e.g. `Makefile`.
+    pub filenames: Vec<String>,
+}
+
+impl FileType {
+    fn merge(&mut self, other: &FileType) {
+        for ext in &other.extensions {
+            if !self.extensions.contains(ext) {
+                self.extensions.push(ext.clone());
+            }
+        }
+        for name in &other.filenames {
+            if !self.filenames.contains(name) {
+                self.filenames.push(name.clone());
+            }
+        }
+    }
+}
+
+/// Named file types for `--type`: one per language in the registry, its
+/// `[[type]]` groups, and any `--type-add` definitions.
+#[derive(Debug, Clone)]
+pub struct Types {
+    types: BTreeMap<String, FileType>,
+}
+
+impl Types {
+    /// The types defined by `registry`.
+    pub fn new(registry: &Registry) -> Result<Types> {
+        let mut types: BTreeMap<String, FileType> = BTreeMap::new();
+
+        for lang in registry.languages().iter().rev() {
+            let file_type = FileType {
+                extensions: lang
+                    .extensions
+                    .iter()
+                    .map(|ext| ext.to_ascii_lowercase())
+                    .collect(),
+                filenames: lang.filenames.clone(),
+            };
+            types.insert(lang.name.to_ascii_lowercase(), file_type);
+        }
+
+        let mut out = Types { types };
+        for group in registry.types() {
+            let mut file_type = FileType {
+                extensions: group
+                    .extensions
+                    .iter()
+                    .map(|ext| ext.to_ascii_lowercase())
+                    .collect(),
+                filenames: group.filenames.clone(),
+            };
+            for name in &group.include {
+                file_type.merge(out.get(name, &group.name)?);
+            }
+            out.types.insert(group.name.to_ascii_lowercase(), file_type);
+        }
+
+        Ok(out)
+    }
+
+    fn get(&self, name: &str, defining: &str) -> Result<&FileType> {
+        match self.types.get(&name.trim().to_ascii_lowercase()) {
+            Some(file_type) => Ok(file_type),
+            None => bail!("Type {defining} includes unknown type {name}"),
+        }
+    }
+
+    /// Add to (or create) a type from a `--type-add` spec:
+    /// `NAME:GLOB[,GLOB...]`, where each glob is `*.EXT` or an exact file
+    /// name, or `NAME:include:TYPE[,TYPE...]`.
+    pub fn add(&mut self, spec: &str) -> Result<()> {
+        let Some((name, rest)) = spec.split_once(':') else {
+            bail!(
+                "Invalid --type-add {spec}: expected NAME:GLOB[,GLOB...] or NAME:include:TYPE[,TYPE...]"
+            );
+        };
+        let name = name.trim().to_ascii_lowercase();
+        if name.is_empty() {
+            bail!("Invalid --type-add {spec}: missing type name");
+        }
+
+        let mut added = FileType::default();
+        if let Some(includes) = rest.strip_prefix("include:") {
+            for include in includes.split(',') {
+                added.merge(self.get(include, &name)?);
+            }
+        } else {
+            for glob in rest.split(',').map(str::trim).filter(|g| !g.is_empty()) {
+                let literal = |s: &str| !s.is_empty() && !s.contains(['*', '?', '[', '{', '/']);
+                match glob.strip_prefix("*.") {
+                    Some(ext) if literal(ext) && !ext.contains('.') => {
+                        added.extensions.push(ext.to_ascii_lowercase());
+                    }
+                    None if literal(glob) => added.filenames.push(glob.to_string()),
+                    _ => bail!(
+                        "Invalid --type-add {spec}: unsupported pattern {glob} (use *.EXT or an exact file name)"
+                    ),
+                }
+            }
+        }
+
+        self.types.entry(name).or_default().merge(&added);
+        Ok(())
+    }
+
+    /// Resolve `--type` values into extensions and exact file names. A value
+    /// naming a type selects all of its files; anything else, or a value
+    /// with a leading dot, is a plain extension.
+    pub fn select(&self, values: &[String]) -> (HashSet<String>, HashSet<String>) {
+        let mut exts = HashSet::new();
+        let mut filenames = HashSet::new();
+
+        for value in values {
+            let value = value.trim().to_ascii_lowercase();
+            if let Some(ext) = value.strip_prefix('.') {
+                if !ext.is_empty() {
+                    exts.insert(ext.to_string());
+                }
+                continue;
+            }
+
+            match self.types.get(&value) {
+                Some(file_type) => {
+                    exts.extend(file_type.extensions.iter().cloned());
+                    filenames.extend(file_type.filenames.iter().cloned());
+                }
+                None if !value.is_empty() => {
+                    exts.insert(value);
+                }
+                None => {}
+            }
+        }
+
+        (exts, filenames)
+    }
+
+    /// One line per type, as printed by `--type-list`, e.g.
+    /// `make: *.mk, *.mak, Makefile`.
+    pub fn list(&self) -> String {
+        let mut out = String::new();
+        for (name, file_type) in &self.types {
+            let globs: Vec<String> = file_type
+                .extensions
+                .iter()
+                .map(|ext| format!("*.{ext}"))
+                .chain(file_type.filenames.iter().cloned())
+                .collect();
+            out.push_str(&format!("{name}: {}\n", globs.join(", ")));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(set: HashSet<String>) -> Vec<String> {
+        let mut v: Vec<_> = set.into_iter().collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn names_expand_and_raw_extensions_still_work() -> Result<()> {
+        let types = Types::new(Registry::builtin())?;
+
+        let (exts, filenames) = types.select(&["c".into(), "PY".into(), "make".into()]);
+        assert_eq!(sorted(exts), ["c", "h", "mak", "mk", "py"]);
+        assert_eq!(sorted(filenames), ["GNUmakefile", "Makefile", "makefile"]);
+
+        // A leading dot always means the bare extension.
+        let (exts, _) = types.select(&[".c".into(), "python".into()]);
+        assert_eq!(sorted(exts), ["c", "py", "pyi"]);
+
+        let (exts, _) = types.select(&["web".into()]);
+        assert!(exts.contains("tsx") && exts.contains("css") && exts.contains("vue"));
+
+        let (exts, _) = types.select(&["cpp".into()]);
+        assert!(exts.contains("cpp") && exts.contains("hpp"));
+        Ok(())
+    }
+
+    #[test]
+    fn types_named_like_an_extension_include_it() -> Result<()> {
+        let registry = Registry::builtin();
+        let types = Types::new(registry)?;
+
+        for lang in registry.languages() {
+            for ext in &lang.extensions {
+                let ext = ext.to_ascii_lowercase();
+                let (exts, _) = types.select(std::slice::from_ref(&ext));
+                assert!(exts.contains(&ext), "-t {ext} must still select *.{ext}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn type_add_defines_and_extends_types() -> Result<()> {
+        let mut types = Types::new(Registry::builtin())?;
+        types.add("tmpl:*.tmpl,*.J2,Justfile")?;
+        types.add("tmpl:*.jinja")?;
+        types.add("src:include:rust,tmpl")?;
+        types.add("c:*.inc")?;
+
+        let (exts, filenames) = types.select(&["src".into()]);
+        assert_eq!(sorted(exts), ["j2", "jinja", "rs", "tmpl"]);
+        assert_eq!(sorted(filenames), ["Justfile"]);
+        let (exts, _) = types.select(&["c".into()]);
+        assert_eq!(sorted(exts), ["c", "h", "inc"]);
+        assert!(
+            types
+                .list()
+                .contains("tmpl: *.tmpl, *.j2, *.jinja, Justfile\n")
+        );
+
+        assert!(types.add("bad").is_err());
+        assert!(types.add("bad:*.tar.gz").is_err());
+        assert!(types.add("bad:src/*.rs").is_err());
+        assert!(types.add("bad:include:nope").is_err());
+        Ok(())
+    }
+}